@@ -1,55 +1,98 @@
 use codspeed_go_runner::results::raw_result::RawResult;
+use std::path::Path;
 use std::time::Duration;
 use tempfile::TempDir;
 
 const TIME_ENTRIES: [usize; 5] = [100_000, 500_000, 1_000_000, 5_000_000, 10_000_000];
 const FILE_COUNT: [usize; 3] = [5, 10, 25];
+const ROUNDS: [usize; 3] = [1_000_000, 10_000_000, 25_000_000];
+
+/// Writes a raw result with the given rounds, in the format of the overlay.
+fn write_raw_result(path: &Path, iters_per_round: &[u64], times_per_round: &[u64]) {
+    let json = serde_json::json!({
+        "schema_version": RawResult::SCHEMA_VERSION,
+        "name": "foo",
+        "uri": "foo",
+        "pid": 42,
+        "status": "ok",
+        "codspeed_iters_per_round": iters_per_round,
+        "codspeed_time_per_round_ns": times_per_round,
+    });
+    std::fs::write(path, serde_json::to_vec(&json).unwrap()).unwrap();
+}
+
+/// Collects the raw results of `profile_dir`, and checks that the results were written.
+fn collect_results(profile_dir: &mut TempDir) {
+    if let Err(error) =
+        codspeed_go_runner::collect_walltime_results(profile_dir.path(), None, &Default::default())
+    {
+        panic!("Collecting results failed: {error}");
+    }
+
+    // Ensure that we have a results folder with the pid
+    let results_dir: std::path::PathBuf = profile_dir.path().join("results");
+    assert!(results_dir.exists());
+    let result_file = results_dir.join("42.json");
+    assert!(result_file.exists());
+}
+
+fn raw_results_dir() -> (TempDir, std::path::PathBuf) {
+    let profile_dir = TempDir::new().unwrap();
+    let raw_results = profile_dir.path().join("raw_results");
+    std::fs::create_dir(&raw_results).unwrap();
+    (profile_dir, raw_results)
+}
 
 #[divan::bench(args = FILE_COUNT, consts = TIME_ENTRIES, max_time = Duration::from_secs(5))]
 fn bench_collect_results<const N: usize>(bencher: divan::Bencher, file_count: usize) {
     use rand::prelude::*;
 
-    fn random_raw_result<const N: usize>(rng: &mut StdRng) -> RawResult {
-        let times_per_round = (0..N).map(|_| rng.random::<u64>() % 1_000_000).collect();
-        let iters_per_round = (0..N).map(|_| rng.random::<u64>() % 1_000 + 1).collect();
-        RawResult {
-            name: "foo".into(),
-            uri: "foo".into(),
-            pid: 42,
-            codspeed_time_per_round_ns: times_per_round,
-            codspeed_iters_per_round: iters_per_round,
-        }
-    }
-
     bencher
         .with_inputs(|| {
             let mut rng = StdRng::seed_from_u64(42);
+            let (profile_dir, raw_results) = raw_results_dir();
 
-            let profile_dir = TempDir::new().unwrap();
-            let raw_results = profile_dir.path().join("raw_results");
-            std::fs::create_dir(&raw_results).unwrap();
-
-            for (i, raw_result) in (0..file_count)
-                .map(|_| random_raw_result::<N>(&mut rng))
-                .enumerate()
-            {
-                let json = serde_json::to_string(&raw_result).unwrap();
-                std::fs::write(raw_results.join(format!("{i}.json")), json).unwrap();
+            for i in 0..file_count {
+                let times_per_round: Vec<u64> =
+                    (0..N).map(|_| rng.random::<u64>() % 1_000_000).collect();
+                let iters_per_round: Vec<u64> =
+                    (0..N).map(|_| rng.random::<u64>() % 1_000 + 1).collect();
+                write_raw_result(
+                    &raw_results.join(format!("{i}.json")),
+                    &iters_per_round,
+                    &times_per_round,
+                );
             }
 
             profile_dir
         })
-        .bench_refs(|profile_dir| {
-            if let Err(error) = codspeed_go_runner::collect_walltime_results(profile_dir.path()) {
-                panic!("Collecting results failed: {error}");
-            }
+        .bench_refs(collect_results);
+}
+
+/// A single benchmark with a very large number of rounds and constant iterations, like a fast
+/// benchmark measured with a long benchtime. The rounds are streamed into the statistics, so the
+/// peak memory doesn't grow with the number of rounds.
+#[divan::bench(args = ROUNDS, max_time = Duration::from_secs(5))]
+fn bench_collect_large_rounds(bencher: divan::Bencher, rounds: usize) {
+    use rand::prelude::*;
 
-            // Ensure that we have a results folder with the pid
-            let results_dir: std::path::PathBuf = profile_dir.path().join("results");
-            assert!(results_dir.exists());
-            let result_file = results_dir.join("42.json");
-            assert!(result_file.exists());
-        });
+    bencher
+        .with_inputs(|| {
+            let mut rng = StdRng::seed_from_u64(42);
+            let (profile_dir, raw_results) = raw_results_dir();
+
+            let times_per_round: Vec<u64> = (0..rounds)
+                .map(|_| 1_000 + rng.random::<u64>() % 100)
+                .collect();
+            write_raw_result(
+                &raw_results.join("0.json"),
+                &vec![1; rounds],
+                &times_per_round,
+            );
+
+            profile_dir
+        })
+        .bench_refs(collect_results);
 }
 
 fn main() {
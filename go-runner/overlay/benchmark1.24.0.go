@@ -182,6 +182,9 @@ func (b *B) ResetTimer() {
 	b.codspeedTimePerRoundNs = b.codspeedTimePerRoundNs[:0]
 	b.startTimestamps = b.startTimestamps[:0]
 	b.stopTimestamps = b.stopTimestamps[:0]
+	b.codspeedTruncation = nil
+	b.codspeedParallelOps = 0
+	b.codspeedParallelOpsPerRound = b.codspeedParallelOpsPerRound[:0]
 }
 
 // SetBytes records the number of bytes processed in a single operation.
@@ -785,7 +788,7 @@ func (s *benchState) processBench(b *B) {
 						chatty: b.chatty,
 						bench:  true,
 					},
-					codspeed:  b.codspeed,
+					codspeed:  b.codspeed.inherited(),
 					benchFunc: b.benchFunc,
 					benchTime: b.benchTime,
 				}
@@ -799,10 +802,17 @@ func (s *benchState) processBench(b *B) {
 				// We print it all, regardless, because we don't want to trim the reason
 				// the benchmark failed.
 				fmt.Fprintf(b.w, "%s--- FAIL: %s\n%s", b.chatty.prefix(), benchName, b.output)
+				saveCodspeedStatus(b)
 				continue
 			}
 			results := r.String()
-			saveCodspeedResults(b, r, benchName)
+			if b.Skipped() {
+				// Skipped during the measured run, the rounds executed before are discarded
+				ensureBenchmarkIsStopped(b)
+				saveCodspeedStatus(b)
+			} else {
+				saveCodspeedResults(b, r, benchName)
+			}
 
 			if b.chatty != nil {
 				fmt.Fprintf(b.w, "%-*s\t", s.maxLen, benchName)
@@ -864,7 +874,7 @@ func (b *B) Run(name string, f func(b *B)) bool {
 			chatty:  b.chatty,
 			bench:   true,
 		},
-		codspeed:   b.codspeed,
+		codspeed:   b.codspeed.inherited(),
 		importPath: b.importPath,
 		benchFunc:  f,
 		benchTime:  b.benchTime,
@@ -898,6 +908,8 @@ func (b *B) Run(name string, f func(b *B)) bool {
 
 	if sub.run1() {
 		sub.run()
+	} else {
+		saveCodspeedStatus(sub)
 	}
 	b.add(sub.result)
 	return !sub.failed
@@ -944,10 +956,11 @@ func (b *B) trimOutput() {
 
 // A PB is used by RunParallel for running parallel benchmarks.
 type PB struct {
-	globalN *atomic.Uint64 // shared between all worker goroutines iteration counter
-	grain   uint64         // acquire that many iterations from globalN at once
-	cache   uint64         // local cache of acquired iterations
-	bN      uint64         // total number of iterations to execute (b.N)
+	globalN  *atomic.Uint64 // shared between all worker goroutines iteration counter
+	grain    uint64         // acquire that many iterations from globalN at once
+	cache    uint64         // local cache of acquired iterations
+	bN       uint64         // total number of iterations to execute (b.N)
+	acquired uint64         // total number of iterations acquired from globalN, see codspeedParallelOps
 }
 
 // Next reports whether there are more iterations to execute.
@@ -961,6 +974,7 @@ func (pb *PB) Next() bool {
 		} else {
 			return false
 		}
+		pb.acquired += pb.cache
 	}
 	pb.cache--
 	return true
@@ -1000,7 +1014,14 @@ func (b *B) RunParallel(body func(*PB)) {
 	}
 
 	var n atomic.Uint64
-	numProcs := b.parallelism * runtime.GOMAXPROCS(0)
+	gomaxprocs := runtime.GOMAXPROCS(0)
+	numProcs := b.parallelism * gomaxprocs
+	b.codspeedParallelism = &codspeedParallelism{
+		Goroutines:  numProcs,
+		Parallelism: b.parallelism,
+		Gomaxprocs:  gomaxprocs,
+	}
+	var ops atomic.Int64
 	var wg sync.WaitGroup
 	wg.Add(numProcs)
 	for p := 0; p < numProcs; p++ {
@@ -1011,10 +1032,13 @@ func (b *B) RunParallel(body func(*PB)) {
 				grain:   grain,
 				bN:      uint64(b.N),
 			}
+			// Iterations left in the cache weren't executed, e.g. when the body exits early
+			defer func() { ops.Add(int64(pb.acquired - pb.cache)) }()
 			body(pb)
 		}()
 	}
 	wg.Wait()
+	b.codspeedParallelOps += ops.Load()
 	if n.Load() <= uint64(b.N) && !b.Failed() {
 		b.Fatal("RunParallel: body exited without pb.Next() == false")
 	}
@@ -5,25 +5,101 @@ import (
 	"encoding/hex"
 	"encoding/json"
 	"fmt"
+	"math"
 	"os"
 	"path/filepath"
 	"reflect"
 	"runtime"
+	"runtime/debug"
+	"strconv"
 	"strings"
+	"sync"
 	"time"
 )
 
+var codspeedProfileDir = "@@CODSPEED_PROFILE_DIR@@" // NOTE: This will be replaced by the go-runner
+
+// Whether the instrument hooks events are received by the go-runner (--local-hooks), rather than
+// by the CodSpeed runner.
+var codspeedLocalHooks = "@@CODSPEED_LOCAL_HOOKS@@" == "true" // NOTE: This will be replaced by the go-runner
+
 func writeGoEnvironment(hooks *InstrumentHooks) {
 	hooks.SetEnvironment("go", "version", runtime.Version())
+	hooks.SetEnvironment("go", "goos", runtime.GOOS)
+	hooks.SetEnvironment("go", "goarch", runtime.GOARCH)
+	hooks.SetEnvironment("go", "compiler", runtime.Compiler)
+
+	if buildInfo, ok := debug.ReadBuildInfo(); ok {
+		// Build settings, e.g. CGO_ENABLED, GOAMD64/GOARM64, GOEXPERIMENT, -tags, -gcflags and -ldflags
+		for _, setting := range buildInfo.Settings {
+			hooks.SetEnvironment("go.build", strings.TrimPrefix(setting.Key, "-"), setting.Value)
+		}
+		for _, dep := range buildInfo.Deps {
+			hooks.SetEnvironment("go.deps", dep.Path, moduleVersion(dep))
+		}
+	}
+
 	hooks.WriteEnvironment(int32(os.Getpid()))
 }
 
+// moduleVersion returns the version of a module dependency, including its replacement if any.
+func moduleVersion(module *debug.Module) string {
+	if module.Replace == nil {
+		return module.Version
+	}
+	if module.Replace.Version == "" {
+		// Replaced by a local directory
+		return fmt.Sprintf("%s => %s", module.Version, module.Replace.Path)
+	}
+	return fmt.Sprintf("%s => %s %s", module.Version, module.Replace.Path, module.Replace.Version)
+}
+
+// writeEnvironmentFile writes the environment to `<profile dir>/environment/<pid>.json`, so that
+// the go-runner can add it to the run metadata. Only used with --local-hooks, since the CodSpeed
+// runner receives the environment through the instrument hooks.
+func writeEnvironmentFile(pid int32, environment map[string]map[string]string) {
+	if !codspeedLocalHooks {
+		return
+	}
+
+	environmentDir := filepath.Join(codspeedProfileDir, "environment")
+	if err := os.MkdirAll(environmentDir, 0755); err != nil {
+		fmt.Fprintf(os.Stderr, "failed to create environment directory: %v\n", err)
+		return
+	}
+	output, err := json.MarshalIndent(environment, "", "  ")
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "failed to marshal environment: %v\n", err)
+		return
+	}
+	environmentFile := filepath.Join(environmentDir, fmt.Sprintf("%d.json", pid))
+	if err := os.WriteFile(environmentFile, output, 0644); err != nil {
+		fmt.Fprintf(os.Stderr, "failed to write environment: %v\n", err)
+	}
+}
+
 type codspeed struct {
 	instrument_hooks *InstrumentHooks
 
 	codspeedTimePerRoundNs []time.Duration
 	codspeedItersPerRound  []int64
 
+	// Iterations and wall time of the warmup phase, which are discarded by ResetTimer.
+	codspeedWarmupIters  int64
+	codspeedWarmupTimeNs time.Duration
+
+	// Effective round strategy, only set for benchmarks executed with runBenchmarkWithWarmup.
+	codspeedRoundConfig *codspeedRoundConfig
+
+	// Set when the benchmark was stopped before executing all the planned rounds.
+	codspeedTruncation *codspeedTruncation
+
+	// Only set for benchmarks calling b.RunParallel. The operations executed by the goroutines
+	// are accumulated in codspeedParallelOps, and saved for every round like the iterations.
+	codspeedParallelism         *codspeedParallelism
+	codspeedParallelOps         int64
+	codspeedParallelOpsPerRound []int64
+
 	startTimestamp  uint64
 	startTimestamps []uint64
 	stopTimestamps  []uint64
@@ -37,10 +113,72 @@ type codspeed struct {
 	loopStartTime time.Time
 }
 
-// This is used to determine the maximum time a benchmark is allowed to run, with the overhead of our
-// modifications in the testing package included. When using a benchtime=3s and multiplier of 3, the
-// benchmark can run at most 9s.
-const BenchMaxTimeMult = 3
+// inherited returns the state shared by the sub-benchmarks and the re-created benchmarks of
+// processBench. The measurements and the round strategy belong to each benchmark.
+func (c *codspeed) inherited() codspeed {
+	return codspeed{
+		instrument_hooks: c.instrument_hooks,
+	}
+}
+
+// Round strategy, configured by the go-runner:
+//   - codspeedMinRounds: number of rounds, unless the benchmark is too slow to run that many.
+//   - codspeedWarmupFraction: duration of the warmup phase, relative to the benchtime.
+//   - codspeedMaxTimeMultiplier: used to determine the maximum time a benchmark is allowed to run,
+//     with the overhead of our modifications in the testing package included. When using a
+//     benchtime=3s and multiplier of 3, the benchmark can run at most 9s.
+var (
+	codspeedMinRounds         = mustParseInt("@@CODSPEED_MIN_ROUNDS@@")            // NOTE: This will be replaced by the go-runner
+	codspeedWarmupFraction    = mustParseFloat("@@CODSPEED_WARMUP_FRACTION@@")     // NOTE: This will be replaced by the go-runner
+	codspeedMaxTimeMultiplier = mustParseFloat("@@CODSPEED_MAX_TIME_MULTIPLIER@@") // NOTE: This will be replaced by the go-runner
+)
+
+// codspeedRoundConfig is the effective round strategy of a benchmark, reported in the results.
+type codspeedRoundConfig struct {
+	MaxRounds int `json:"max_rounds"`
+	// Time of a round if the benchmark runs at the speed estimated by the warmup, the rounds
+	// aren't stopped or extended to match it.
+	TargetRoundTimeNs time.Duration `json:"target_round_time_ns"`
+	MaxTimeNs         time.Duration `json:"max_time_ns"`
+}
+
+// codspeedTruncation describes why a benchmark executed fewer rounds than planned.
+type codspeedTruncation struct {
+	Reason        string `json:"reason"`
+	PlannedRounds int64  `json:"planned_rounds"`
+	ActualRounds  int64  `json:"actual_rounds"`
+}
+
+// codspeedParallelism describes the goroutines started by b.RunParallel.
+type codspeedParallelism struct {
+	Goroutines  int `json:"goroutines"`
+	Parallelism int `json:"parallelism"`
+	Gomaxprocs  int `json:"gomaxprocs"`
+}
+
+// The benchmark exceeded benchMaxTime.
+const truncationReasonMaxTime = "max_time_exceeded"
+
+func mustParseInt(value string) int {
+	parsed, err := strconv.Atoi(value)
+	if err != nil {
+		panic(fmt.Sprintf("codspeed: invalid round strategy value %q: %v", value, err))
+	}
+	return parsed
+}
+
+func mustParseFloat(value string) float64 {
+	parsed, err := strconv.ParseFloat(value, 64)
+	if err != nil {
+		panic(fmt.Sprintf("codspeed: invalid round strategy value %q: %v", value, err))
+	}
+	return parsed
+}
+
+// benchMaxTime returns the maximum time a benchmark with the given benchtime is allowed to run.
+func benchMaxTime(benchTime time.Duration) time.Duration {
+	return time.Duration(float64(benchTime) * codspeedMaxTimeMultiplier)
+}
 
 // Modified version of the `stopOrScaleLoop` function to also take into account the
 // overhead of start/stop the timer each loop iteration.
@@ -56,7 +194,17 @@ const BenchMaxTimeMult = 3
 func (b *B) stopOrScaleBLoopCodspeed() bool {
 	// The total duration must be at most N times the requested benchtime
 	actualT := time.Since(b.loopStartTime)
-	if actualT >= b.benchTime.d*BenchMaxTimeMult {
+	if actualT >= benchMaxTime(b.benchTime.d) {
+		// Every b.Loop() iteration is measured as a round. We didn't reach the benchtime, so we
+		// would have scaled the loop once more.
+		if b.Elapsed() < b.benchTime.d {
+			prevIters := int64(b.loop.n)
+			b.codspeedTruncation = &codspeedTruncation{
+				Reason:        truncationReasonMaxTime,
+				PlannedRounds: int64(predictN(b.benchTime.d.Nanoseconds(), prevIters, actualT.Nanoseconds(), prevIters)),
+				ActualRounds:  int64(b.loop.i),
+			}
+		}
 		return false
 	}
 
@@ -78,17 +226,22 @@ func (b *B) stopOrScaleBLoopCodspeed() bool {
 	return true
 }
 
-func findGitRoot() (string, error) {
-	cwd, err := os.Getwd()
-	if err != nil {
-		return "", err
-	}
+// Configures how the benchmark URIs are computed. The mode is either "git" (relative to the git
+// root, or the module root if the project isn't in a git repository), "module" (relative to the
+// module root) or "path" (relative to codspeedUriRoot).
+var (
+	codspeedUriRootMode = "@@CODSPEED_URI_ROOT_MODE@@" // NOTE: This will be replaced by the go-runner
+	codspeedUriRoot     = "@@CODSPEED_URI_ROOT@@"      // NOTE: This will be replaced by the go-runner
+)
+
+var uriRootFallbackOnce sync.Once
 
-	// Search up the directory tree for .git
-	currentDir := cwd
+// findParentDir searches up the directory tree, starting at startDir, for the first directory
+// matching isRoot.
+func findParentDir(startDir string, isRoot func(dir string) bool) (string, error) {
+	currentDir := startDir
 	for {
-		gitRoot := filepath.Join(currentDir, ".git")
-		if _, err := os.Stat(gitRoot); err == nil {
+		if isRoot(currentDir) {
 			return currentDir, nil
 		}
 
@@ -103,23 +256,110 @@ func findGitRoot() (string, error) {
 	return "", os.ErrNotExist
 }
 
-func getGitRelativePath(absPath string) string {
+// isGitRoot checks whether dir contains a `.git` directory, or a `.git` file pointing to the
+// git directory of a worktree. Submodules also have a `.git` file, but they're part of their
+// parent repository, so the search continues upward.
+func isGitRoot(dir string) bool {
+	gitPath := filepath.Join(dir, ".git")
+	info, err := os.Stat(gitPath)
+	if err != nil {
+		return false
+	}
+	if info.IsDir() {
+		return true
+	}
+
+	gitDir, ok := readGitDirPointer(dir, gitPath)
+	return ok && isWorktreeGitDir(gitDir)
+}
+
+// readGitDirPointer returns the git directory referenced by a `.git` file (`gitdir: <path>`).
+func readGitDirPointer(dir, gitPath string) (string, bool) {
+	content, err := os.ReadFile(gitPath)
+	if err != nil {
+		return "", false
+	}
+	gitDir, ok := strings.CutPrefix(strings.TrimSpace(string(content)), "gitdir:")
+	if !ok {
+		return "", false
+	}
+
+	gitDir = strings.TrimSpace(gitDir)
+	if !filepath.IsAbs(gitDir) {
+		gitDir = filepath.Join(dir, gitDir)
+	}
+	return gitDir, true
+}
+
+// isWorktreeGitDir checks whether gitDir is the git directory of a worktree
+// (`<repo>/.git/worktrees/<name>`), which has a `commondir` file pointing to the git directory of
+// the main worktree. The git directories of submodules (`<repo>/.git/modules/<name>`) don't.
+func isWorktreeGitDir(gitDir string) bool {
+	info, err := os.Stat(filepath.Join(gitDir, "commondir"))
+	return err == nil && !info.IsDir()
+}
+
+func isModuleRoot(dir string) bool {
+	info, err := os.Stat(filepath.Join(dir, "go.mod"))
+	return err == nil && !info.IsDir()
+}
+
+func findGitRoot() (string, error) {
+	cwd, err := os.Getwd()
+	if err != nil {
+		return "", err
+	}
+
+	return findParentDir(cwd, isGitRoot)
+}
+
+// findModuleRoot returns the root of the module containing the benchmark file. If the file isn't
+// part of a module (e.g. GOPATH mode), the directory of the file is used.
+func findModuleRoot(benchFile string) string {
+	benchDir := filepath.Dir(benchFile)
+	if moduleRoot, err := findParentDir(benchDir, isModuleRoot); err == nil {
+		return moduleRoot
+	}
+	return benchDir
+}
+
+func findUriRoot(benchFile string) string {
+	switch codspeedUriRootMode {
+	case "path":
+		return codspeedUriRoot
+	case "module":
+		return findModuleRoot(benchFile)
+	default:
+		gitRoot, err := findGitRoot()
+		if err == nil {
+			return gitRoot
+		}
+
+		moduleRoot := findModuleRoot(benchFile)
+		uriRootFallbackOnce.Do(func() {
+			fmt.Fprintf(os.Stderr, "codspeed: failed to find git root, benchmark URIs are relative to the module root (%s)\n", moduleRoot)
+		})
+		return moduleRoot
+	}
+}
+
+func getUriRelativePath(absPath string) string {
 	canonicalizedAbsPath, err := filepath.EvalSymlinks(absPath)
 	if err != nil {
 		panic(fmt.Sprintf("failed to evaluate symlinks for path %s: %v", absPath, err))
 	}
 
-	gitRoot, err := findGitRoot()
-	if err != nil {
-		panic(fmt.Sprintf("failed to find git root: %v", err))
+	uriRoot := findUriRoot(canonicalizedAbsPath)
+	if canonicalizedUriRoot, err := filepath.EvalSymlinks(uriRoot); err == nil {
+		uriRoot = canonicalizedUriRoot
 	}
 
-	gitRelativePath, err := filepath.Rel(gitRoot, canonicalizedAbsPath)
+	relativePath, err := filepath.Rel(uriRoot, canonicalizedAbsPath)
 	if err != nil {
-		panic(fmt.Sprintf("failed to compute relative path from git root %s to %s: %v", gitRoot, canonicalizedAbsPath, err))
+		panic(fmt.Sprintf("failed to compute relative path from %s to %s: %v", uriRoot, canonicalizedAbsPath, err))
 	}
 
-	return gitRelativePath
+	return filepath.ToSlash(relativePath)
 }
 
 // If the benchmark execution failed, we have to ensure to stop the benchmark, which
@@ -156,32 +396,157 @@ func removeFolderFromPath(path string, folder string) string {
 	return filepath.Join(newParts...)
 }
 
+// reportedMetrics returns a copy of the metrics reported with b.ReportMetric. Values that can't be
+// represented in JSON (NaN and infinities) are dropped with a warning.
+func reportedMetrics(extra map[string]float64, benchUri string) map[string]float64 {
+	metrics := make(map[string]float64, len(extra))
+	for unit, value := range extra {
+		if math.IsNaN(value) || math.IsInf(value, 0) {
+			fmt.Fprintf(os.Stderr, "codspeed: ignoring metric %q of %s: invalid value %v\n", unit, benchUri, value)
+			continue
+		}
+		metrics[unit] = value
+	}
+	return metrics
+}
+
+// codspeedRawResultsVersion is the version of the codspeedRawResults format. It must be bumped
+// whenever the format changes in a way that older go-runners can't parse.
+const codspeedRawResultsVersion = 1
+
+// codspeedRawResults is the raw result of a benchmark, which is parsed by the go-runner.
+//
+// WARN: Keep in sync with the go-runner (src/results/raw_result.rs)
+type codspeedRawResults struct {
+	// Set to codspeedRawResultsVersion by writeRawResults
+	SchemaVersion int `json:"schema_version"`
+	codspeedBenchmarkInfo
+
+	Pid     int    `json:"pid"`
+	Status  string `json:"status"`
+	Message string `json:"message,omitempty"`
+	// The iterations are written before the times, since the go-runner buffers the first of the
+	// two arrays to pair their values, and the iterations are the same for most rounds
+	CodspeedItersPerRound  []int64              `json:"codspeed_iters_per_round"`
+	CodspeedTimePerRoundNs []time.Duration      `json:"codspeed_time_per_round_ns"`
+	BytesPerOp             int64                `json:"bytes_per_op,omitempty"`
+	CodspeedWarmupIters    int64                `json:"codspeed_warmup_iters,omitempty"`
+	CodspeedWarmupTimeNs   time.Duration        `json:"codspeed_warmup_time_ns,omitempty"`
+	CodspeedRoundConfig    *codspeedRoundConfig `json:"codspeed_round_config,omitempty"`
+	CodspeedTruncation     *codspeedTruncation  `json:"codspeed_truncation,omitempty"`
+	CodspeedParallelism    *codspeedParallelism `json:"codspeed_parallelism,omitempty"`
+	CodspeedParallelOps    []int64              `json:"codspeed_parallel_ops_per_round,omitempty"`
+	Extra                  map[string]float64   `json:"extra,omitempty"`
+}
+
+// codspeedBenchmarkInfo identifies a benchmark and where it is defined.
+type codspeedBenchmarkInfo struct {
+	// Name of the benchmark, with the sub-benchmarks separated by `::`
+	Name string `json:"name"`
+	Uri  string `json:"uri"`
+	// Line of the benchmark function in the benchmark file
+	Line int `json:"line,omitempty"`
+	// Import path of the package containing the benchmark (B.importPath)
+	Package string `json:"package,omitempty"`
+	// Fully qualified name of the benchmark function, e.g. `example.com/pkg_test.BenchmarkFoo`
+	Function string `json:"function,omitempty"`
+}
+
+// Benchmark statuses, see codspeedRawResults.Status
+const (
+	codspeedStatusOk      = "ok"
+	codspeedStatusFailed  = "failed"
+	codspeedStatusSkipped = "skipped"
+)
+
+// Only keep the end of the benchmark output, which contains the failure or skip reason.
+const maxStatusMessageLen = 4096
+
 func saveCodspeedResults(b *B, r BenchmarkResult, benchName string) {
-	type RawResults struct {
-		Name                   string          `json:"name"`
-		Uri                    string          `json:"uri"`
-		Pid                    int             `json:"pid"`
-		CodspeedTimePerRoundNs []time.Duration `json:"codspeed_time_per_round_ns"`
-		CodspeedItersPerRound  []int64         `json:"codspeed_iters_per_round"`
+	info, ok := codspeedBenchmarkInfoOf(b)
+	if !ok {
+		return
+	}
+
+	writeRawResults(codspeedRawResults{
+		codspeedBenchmarkInfo:  info,
+		Pid:                    os.Getpid(),
+		Status:                 codspeedStatusOk,
+		CodspeedTimePerRoundNs: r.CodspeedTimePerRoundNs,
+		CodspeedItersPerRound:  r.CodspeedItersPerRound,
+		BytesPerOp:             r.Bytes,
+		CodspeedWarmupIters:    b.codspeedWarmupIters,
+		CodspeedWarmupTimeNs:   b.codspeedWarmupTimeNs,
+		CodspeedRoundConfig:    b.codspeedRoundConfig,
+		CodspeedTruncation:     b.codspeedTruncation,
+		CodspeedParallelism:    b.codspeedParallelism,
+		CodspeedParallelOps:    b.codspeedParallelOpsPerRound,
+		Extra:                  reportedMetrics(r.Extra, info.Uri),
+	})
+
+	// Send pid and executed benchmark to the runner
+	b.codspeed.instrument_hooks.SetExecutedBenchmark(int32(os.Getpid()), info.Uri)
+}
+
+// saveCodspeedStatus records a benchmark that failed or was skipped, so that it doesn't silently
+// disappear from the results. Benchmarks with sub-benchmarks aren't recorded, since their status
+// is already reported by the sub-benchmarks (a failure is propagated to the parent).
+func saveCodspeedStatus(b *B) {
+	var status string
+	switch {
+	case b.hasSub.Load():
+		return
+	case b.Failed():
+		status = codspeedStatusFailed
+	case b.Skipped():
+		status = codspeedStatusSkipped
+	default:
+		return
+	}
+
+	info, ok := codspeedBenchmarkInfoOf(b)
+	if !ok {
+		return
 	}
 
+	b.mu.RLock()
+	message := strings.TrimSpace(string(b.output))
+	b.mu.RUnlock()
+	if len(message) > maxStatusMessageLen {
+		message = "..." + message[len(message)-maxStatusMessageLen:]
+	}
+
+	writeRawResults(codspeedRawResults{
+		codspeedBenchmarkInfo: info,
+		Pid:                   os.Getpid(),
+		Status:                status,
+		Message:               message,
+
+		CodspeedTimePerRoundNs: []time.Duration{},
+		CodspeedItersPerRound:  []int64{},
+	})
+}
+
+// codspeedBenchmarkInfoOf returns the name of the benchmark, its URI and where it is defined.
+func codspeedBenchmarkInfoOf(b *B) (codspeedBenchmarkInfo, bool) {
 	// Find the filename of the benchmark file
-	var benchFile string
+	var benchFile, benchFunc string
+	var benchLine int
 	if b.benchFunc != nil {
 		pc := reflect.ValueOf(b.benchFunc).Pointer()
 		fn := runtime.FuncForPC(pc)
 		if fn == nil {
-			return
+			return codspeedBenchmarkInfo{}, false
 		}
 
-		file, _ := fn.FileLine(pc)
-		benchFile = file
+		benchFile, benchLine = fn.FileLine(pc)
+		benchFunc = fn.Name()
 	}
 
 	if benchFile == "" {
 		panic("Could not determine benchmark file name")
 	}
-	relativeBenchFile := getGitRelativePath(benchFile)
+	relativeBenchFile := getUriRelativePath(benchFile)
 
 	// Build custom bench name with :: separator
 	var nameParts []string
@@ -201,50 +566,110 @@ func saveCodspeedResults(b *B, r BenchmarkResult, benchName string) {
 		}
 		current = current.parent
 	}
-	benchName = strings.Join(nameParts, "::")
-	benchUri := fmt.Sprintf("%s::%s", relativeBenchFile, benchName)
+	benchName := strings.Join(nameParts, "::")
+	return codspeedBenchmarkInfo{
+		Name:     benchName,
+		Uri:      fmt.Sprintf("%s::%s", relativeBenchFile, benchName),
+		Line:     benchLine,
+		Package:  b.importPath,
+		Function: benchFunc,
+	}, true
+}
 
-	rawResults := RawResults{
-		Name:                   benchName,
-		Uri:                    benchUri,
-		Pid:                    os.Getpid(),
-		CodspeedTimePerRoundNs: r.CodspeedTimePerRoundNs,
-		CodspeedItersPerRound:  r.CodspeedItersPerRound,
-	}
+// Raw results transports, see writeRawResults
+const (
+	codspeedRawResultsLog   = "log"
+	codspeedRawResultsFiles = "files"
+)
+
+var codspeedRawResultsTransport = "@@CODSPEED_RAW_RESULTS_TRANSPORT@@" // NOTE: This will be replaced by the go-runner
+
+// codspeedRawResultsLogFile is the log that the raw results of this process are appended to. It's
+// opened on the first write, and never closed since every frame is written at once.
+var codspeedRawResultsLogFile struct {
+	sync.Mutex
+	file *os.File
+	err  error
+}
 
-	profileDir := "@@CODSPEED_PROFILE_DIR@@" // NOTE: This will be replaced by the go-runner
-	if err := os.MkdirAll(filepath.Join(profileDir, "raw_results"), 0755); err != nil {
+// writeRawResults saves the raw results of a benchmark, which are collected by the go-runner
+// after the run. With the log transport, they're appended to `raw_results/<pid>.log`, and fall
+// back to a file per benchmark when the log can't be written.
+func writeRawResults(rawResults codspeedRawResults) {
+	rawResults.SchemaVersion = codspeedRawResultsVersion
+
+	rawResultsDir := filepath.Join(codspeedProfileDir, "raw_results")
+	if err := os.MkdirAll(rawResultsDir, 0755); err != nil {
 		fmt.Fprintf(os.Stderr, "failed to create raw results directory: %v\n", err)
 		return
 	}
+
+	if codspeedRawResultsTransport == codspeedRawResultsLog {
+		err := appendRawResults(rawResultsDir, rawResults)
+		if err == nil {
+			return
+		}
+		fmt.Fprintf(os.Stderr, "failed to append raw results to the log, writing them to a separate file: %v\n", err)
+	}
+	writeRawResultsFile(rawResultsDir, rawResults)
+}
+
+// appendRawResults appends a frame to the raw results log: the length of the JSON, padded to 20
+// digits, on its own line, followed by the JSON and a newline. The length lets the go-runner
+// detect a frame truncated by a crash. Once a write failed, the log isn't used anymore, so that a
+// partially written frame stays the last one.
+func appendRawResults(rawResultsDir string, rawResults codspeedRawResults) error {
+	data, err := json.Marshal(rawResults)
+	if err != nil {
+		return err
+	}
+	frame := []byte(fmt.Sprintf("%020d\n", len(data)))
+	frame = append(frame, data...)
+	frame = append(frame, '\n')
+
+	logFile := &codspeedRawResultsLogFile
+	logFile.Lock()
+	defer logFile.Unlock()
+
+	if logFile.file == nil && logFile.err == nil {
+		path := filepath.Join(rawResultsDir, fmt.Sprintf("%d.log", os.Getpid()))
+		logFile.file, logFile.err = os.OpenFile(path, os.O_WRONLY|os.O_CREATE|os.O_APPEND, 0644)
+	}
+	if logFile.err != nil {
+		return logFile.err
+	}
+	if _, err := logFile.file.Write(frame); err != nil {
+		logFile.err = err
+		return err
+	}
+	return nil
+}
+
+// writeRawResultsFile writes the raw results of a benchmark to their own file.
+func writeRawResultsFile(rawResultsDir string, rawResults codspeedRawResults) {
 	// Generate random filename to avoid any overwrites
 	randomBytes := make([]byte, 16)
 	if _, err := rand.Read(randomBytes); err != nil {
 		fmt.Fprintf(os.Stderr, "failed to generate random filename: %v\n", err)
 		return
 	}
-	rawResultsFile := filepath.Join(profileDir, "raw_results", fmt.Sprintf("%s.json", hex.EncodeToString(randomBytes)))
-	file, err := os.Create(rawResultsFile)
+	rawResultsFile := filepath.Join(rawResultsDir, fmt.Sprintf("%s.json", hex.EncodeToString(randomBytes)))
+	file, err := os.OpenFile(rawResultsFile, os.O_WRONLY|os.O_CREATE|os.O_EXCL, 0644)
 	if err != nil {
 		fmt.Fprintf(os.Stderr, "failed to create raw results file: %v\n", err)
 		return
 	}
+	defer file.Close()
+
 	output, err := json.MarshalIndent(rawResults, "", "  ")
 	if err != nil {
 		fmt.Fprintf(os.Stderr, "failed to marshal raw results: %v\n", err)
-		file.Close()
 		return
 	}
-	// FIXME: Don't overwrite the file if it already exists
 	if _, err := file.Write(output); err != nil {
 		fmt.Fprintf(os.Stderr, "failed to write raw results: %v\n", err)
-		file.Close()
 		return
 	}
-	defer file.Close()
-
-	// Send pid and executed benchmark to the runner
-	b.codspeed.instrument_hooks.SetExecutedBenchmark(int32(os.Getpid()), benchUri)
 }
 
 func (b *B) sendAccumulatedTimestamps() {
@@ -287,6 +712,10 @@ func (b *B) SaveMeasurement() {
 
 	b.codspeedItersPerRound = append(b.codspeedItersPerRound, max(int64(b.N), 1))
 	b.codspeedTimePerRoundNs = append(b.codspeedTimePerRoundNs, duration)
+	if b.codspeedParallelism != nil {
+		b.codspeedParallelOpsPerRound = append(b.codspeedParallelOpsPerRound, b.codspeedParallelOps)
+		b.codspeedParallelOps = 0
+	}
 }
 
 // StopTimer stops timing a test. This can be used to pause the timer
@@ -320,8 +749,10 @@ func (b *B) StartTimerWithoutMarker() {
 }
 
 func runBenchmarkWithWarmup(b *B) {
-	warmupD := b.benchTime.d / 10
+	warmupD := time.Duration(float64(b.benchTime.d) * codspeedWarmupFraction)
 	warmupN := int64(1)
+	b.codspeedWarmupIters = 0
+	b.codspeedWarmupTimeNs = 0
 	for n := int64(1); !b.failed && b.duration < warmupD && n < 1e9; {
 		last := n
 		// Predict required iterations.
@@ -336,6 +767,8 @@ func runBenchmarkWithWarmup(b *B) {
 		b.duration = time.Since(start)
 
 		warmupN = n
+		b.codspeedWarmupIters += n
+		b.codspeedWarmupTimeNs += b.duration
 	}
 
 	// Reset the fields from the warmup run
@@ -348,7 +781,7 @@ func runBenchmarkWithWarmup(b *B) {
 	// When we have a very slow benchmark (e.g. taking 500ms), we have to:
 	// 1. Reduce the number of rounds to not slow down the process (e.g. by executing a 1s bench 100 times)
 	// 2. Not end up with roundN of 0 when dividing benchN (which can be < 100) by rounds
-	const minRounds = 100
+	minRounds := codspeedMinRounds
 	var rounds int
 	var roundN int
 	if benchN < minRounds {
@@ -358,15 +791,27 @@ func runBenchmarkWithWarmup(b *B) {
 		rounds = minRounds
 		roundN = benchN / int(rounds)
 	}
+	b.codspeedRoundConfig = &codspeedRoundConfig{
+		MaxRounds:         rounds,
+		TargetRoundTimeNs: benchD / time.Duration(rounds),
+		MaxTimeNs:         benchMaxTime(benchD),
+	}
 
 	benchStart := time.Now()
 	b.codspeed.instrument_hooks.StartBenchmark()
-	for range rounds {
+	for round := range rounds {
 		b.runN(int(roundN))
 
 		// Ensure that we don't spend too much time running the benchmarks, bail if we exceed
 		// N times the requested benchtime. This is a failsafe, if the N prediction is flawed.
-		if time.Since(benchStart) > benchD*BenchMaxTimeMult {
+		if time.Since(benchStart) > benchMaxTime(benchD) {
+			if round+1 < rounds {
+				b.codspeedTruncation = &codspeedTruncation{
+					Reason:        truncationReasonMaxTime,
+					PlannedRounds: int64(rounds),
+					ActualRounds:  int64(round + 1),
+				}
+			}
 			break
 		}
 	}
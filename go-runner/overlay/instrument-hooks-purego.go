@@ -0,0 +1,286 @@
+package testing
+
+import (
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"runtime"
+	"sync"
+	"syscall"
+	_ "unsafe" // Required for go:linkname
+)
+
+// Pure-Go implementation of the instrument-hooks library, used when cgo isn't available. It
+// speaks the same protocol as the C library: every command is bincode-encoded, prefixed with its
+// length (u32, little-endian) and written to the control FIFO. The runner then acknowledges it
+// through the ack FIFO.
+//
+// The environment commands (hooksCmdSetEnvironment and hooksCmdWriteEnvironment) are an extension
+// of the go-runner, whose encoding differs from the C library, so they're only sent to the
+// go-runner (--local-hooks). The CodSpeed runner doesn't receive the environment with this backend.
+//
+// WARN: Keep in sync with the instrument-hooks library and the go-runner (src/hooks/protocol.rs).
+
+var integrationVersion = "@@GO_RUNNER_VERSION@@"
+
+// FIFOs of the CodSpeed runner, or of the go-runner with --local-hooks.
+var (
+	runnerCtlFifo = "@@CODSPEED_HOOKS_CTL_FIFO@@" // NOTE: This will be replaced by the go-runner
+	runnerAckFifo = "@@CODSPEED_HOOKS_ACK_FIFO@@" // NOTE: This will be replaced by the go-runner
+)
+
+const instrumentHooksProtocolVersion = 1
+
+// Command variants, in the same order as in the runner.
+const (
+	hooksCmdExecutedBenchmark uint32 = iota
+	hooksCmdStartBenchmark
+	hooksCmdStopBenchmark
+	hooksCmdAck
+	hooksCmdPingPerf
+	hooksCmdSetIntegration
+	hooksCmdErr
+	hooksCmdAddMarker
+	hooksCmdSetVersion
+	hooksCmdSetEnvironment
+	hooksCmdWriteEnvironment
+)
+
+// Marker variants, in the same order as in the runner.
+const (
+	hooksMarkerSampleStart uint32 = iota
+	hooksMarkerSampleEnd
+	hooksMarkerBenchmarkStart
+	hooksMarkerBenchmarkEnd
+)
+
+//go:linkname runtime_nanotime runtime.nanotime
+func runtime_nanotime() int64
+
+type InstrumentHooks struct {
+	mu  sync.Mutex
+	ctl *os.File
+	ack *os.File
+
+	environment map[string]map[string]string
+}
+
+func NewInstrumentHooks() *InstrumentHooks {
+	inst := &InstrumentHooks{
+		environment: make(map[string]map[string]string),
+	}
+	inst.connect()
+	runtime.SetFinalizer(inst, (*InstrumentHooks).cleanup)
+	inst.SetIntegration("codspeed-go", integrationVersion)
+	return inst
+}
+
+// connect opens the FIFOs of the runner. If no runner is listening, the hooks stay disconnected
+// and every command is ignored, which matches the behavior of the C library.
+func (i *InstrumentHooks) connect() {
+	if info, err := os.Stat(runnerCtlFifo); err != nil || info.Mode()&os.ModeNamedPipe == 0 {
+		return
+	}
+
+	// Opening the write end in non-blocking mode fails if nobody is reading from the FIFO,
+	// which prevents us from blocking forever on a stale FIFO.
+	ctl, err := os.OpenFile(runnerCtlFifo, os.O_WRONLY|syscall.O_NONBLOCK, 0)
+	if err != nil {
+		return
+	}
+	ack, err := os.OpenFile(runnerAckFifo, os.O_RDONLY, 0)
+	if err != nil {
+		ctl.Close()
+		return
+	}
+	i.ctl = ctl
+	i.ack = ack
+
+	var enc hooksEncoder
+	enc.u32(hooksCmdSetVersion)
+	enc.u64(instrumentHooksProtocolVersion)
+	if err := i.send(enc.buf); err != nil {
+		fmt.Fprintf(os.Stderr, "failed to connect to the CodSpeed runner: %v\n", err)
+		i.Close()
+	}
+}
+
+func (i *InstrumentHooks) Close() {
+	i.mu.Lock()
+	defer i.mu.Unlock()
+
+	if i.ctl != nil {
+		i.ctl.Close()
+		i.ctl = nil
+	}
+	if i.ack != nil {
+		i.ack.Close()
+		i.ack = nil
+	}
+	runtime.SetFinalizer(i, nil)
+}
+
+func (i *InstrumentHooks) cleanup() {
+	i.Close()
+}
+
+// send writes a single command to the runner and waits for its acknowledgement.
+func (i *InstrumentHooks) send(payload []byte) error {
+	i.mu.Lock()
+	defer i.mu.Unlock()
+
+	if i.ctl == nil {
+		return nil
+	}
+
+	frame := make([]byte, 0, 4+len(payload))
+	frame = binary.LittleEndian.AppendUint32(frame, uint32(len(payload)))
+	frame = append(frame, payload...)
+	if _, err := i.ctl.Write(frame); err != nil {
+		return err
+	}
+
+	return i.waitForAck()
+}
+
+func (i *InstrumentHooks) waitForAck() error {
+	var header [4]byte
+	if _, err := io.ReadFull(i.ack, header[:]); err != nil {
+		return err
+	}
+	payload := make([]byte, binary.LittleEndian.Uint32(header[:]))
+	if _, err := io.ReadFull(i.ack, payload); err != nil {
+		return err
+	}
+	if len(payload) < 4 {
+		return errors.New("invalid response from the runner")
+	}
+
+	switch cmd := binary.LittleEndian.Uint32(payload); cmd {
+	case hooksCmdAck:
+		return nil
+	case hooksCmdErr:
+		return errors.New("the runner returned an error")
+	default:
+		return fmt.Errorf("unexpected response from the runner: %d", cmd)
+	}
+}
+
+func (i *InstrumentHooks) sendOrWarn(enc hooksEncoder) {
+	if err := i.send(enc.buf); err != nil {
+		fmt.Fprintf(os.Stderr, "failed to send command to the CodSpeed runner: %v\n", err)
+	}
+}
+
+func (i *InstrumentHooks) SetIntegration(name, version string) {
+	var enc hooksEncoder
+	enc.u32(hooksCmdSetIntegration)
+	enc.str(name)
+	enc.str(version)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) StartBenchmark() {
+	var enc hooksEncoder
+	enc.u32(hooksCmdStartBenchmark)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) StopBenchmark() {
+	var enc hooksEncoder
+	enc.u32(hooksCmdStopBenchmark)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) SetExecutedBenchmark(pid int32, name string) {
+	var enc hooksEncoder
+	enc.u32(hooksCmdExecutedBenchmark)
+	enc.u32(uint32(pid))
+	enc.str(name)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) IsInstrumented() bool {
+	i.mu.Lock()
+	defer i.mu.Unlock()
+	return i.ctl != nil
+}
+
+// CurrentTimestamp returns the same monotonic clock as the C library (CLOCK_MONOTONIC on Linux),
+// so that the markers can be matched with the samples collected by the runner.
+func CurrentTimestamp() uint64 {
+	return uint64(runtime_nanotime())
+}
+
+func (i *InstrumentHooks) addMarker(pid int32, marker uint32, timestamp uint64) {
+	var enc hooksEncoder
+	enc.u32(hooksCmdAddMarker)
+	enc.u32(uint32(pid))
+	enc.u32(marker)
+	enc.u64(timestamp)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) AddBenchmarkTimestamps(startTimestamp, endTimestamp uint64) {
+	pid := int32(os.Getpid())
+	i.addMarker(pid, hooksMarkerBenchmarkStart, startTimestamp)
+	i.addMarker(pid, hooksMarkerBenchmarkEnd, endTimestamp)
+}
+
+func (i *InstrumentHooks) SetEnvironment(sectionName, key, value string) {
+	i.mu.Lock()
+	section, ok := i.environment[sectionName]
+	if !ok {
+		section = make(map[string]string)
+		i.environment[sectionName] = section
+	}
+	section[key] = value
+	i.mu.Unlock()
+
+	if !codspeedLocalHooks {
+		return
+	}
+	// The commands of concurrent processes are interleaved in the FIFO, so the runner needs the
+	// pid to know which process the value belongs to.
+	var enc hooksEncoder
+	enc.u32(hooksCmdSetEnvironment)
+	enc.u32(uint32(os.Getpid()))
+	enc.str(sectionName)
+	enc.str(key)
+	enc.str(value)
+	i.sendOrWarn(enc)
+}
+
+func (i *InstrumentHooks) WriteEnvironment(pid int32) {
+	i.mu.Lock()
+	writeEnvironmentFile(pid, i.environment)
+	i.mu.Unlock()
+
+	if !codspeedLocalHooks {
+		return
+	}
+	var enc hooksEncoder
+	enc.u32(hooksCmdWriteEnvironment)
+	enc.u32(uint32(pid))
+	i.sendOrWarn(enc)
+}
+
+// hooksEncoder encodes commands using the bincode format expected by the runner.
+type hooksEncoder struct {
+	buf []byte
+}
+
+func (e *hooksEncoder) u32(v uint32) {
+	e.buf = binary.LittleEndian.AppendUint32(e.buf, v)
+}
+
+func (e *hooksEncoder) u64(v uint64) {
+	e.buf = binary.LittleEndian.AppendUint64(e.buf, v)
+}
+
+func (e *hooksEncoder) str(v string) {
+	e.u64(uint64(len(v)))
+	e.buf = append(e.buf, v...)
+}
@@ -15,11 +15,15 @@ var integrationVersion = "@@GO_RUNNER_VERSION@@"
 
 type InstrumentHooks struct {
 	hooks *C.InstrumentHooks
+
+	// Copy of the environment, which is also written to the profile folder for the go-runner.
+	environment map[string]map[string]string
 }
 
 func NewInstrumentHooks() *InstrumentHooks {
 	inst := &InstrumentHooks{
-		hooks: C.instrument_hooks_init(),
+		hooks:       C.instrument_hooks_init(),
+		environment: make(map[string]map[string]string),
 	}
 	runtime.SetFinalizer(inst, (*InstrumentHooks).cleanup)
 	inst.SetIntegration("codspeed-go", integrationVersion)
@@ -93,6 +97,13 @@ func (i *InstrumentHooks) AddBenchmarkTimestamps(startTimestamp, endTimestamp ui
 }
 
 func (i *InstrumentHooks) SetEnvironment(sectionName, key, value string) {
+	section, ok := i.environment[sectionName]
+	if !ok {
+		section = make(map[string]string)
+		i.environment[sectionName] = section
+	}
+	section[key] = value
+
 	if i.hooks == nil {
 		return
 	}
@@ -107,6 +118,8 @@ func (i *InstrumentHooks) SetEnvironment(sectionName, key, value string) {
 }
 
 func (i *InstrumentHooks) WriteEnvironment(pid int32) {
+	writeEnvironmentFile(pid, i.environment)
+
 	if i.hooks == nil {
 		return
 	}
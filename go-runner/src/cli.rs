@@ -1,11 +1,173 @@
-#[derive(Debug)]
+use crate::config::RawResultsTransport;
+use crate::results::compare::{CompareOptions, REGRESSION_EXIT_CODE};
+use crate::results::schema::OutlierPolicy;
+use std::{path::PathBuf, str::FromStr};
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum CliExit {
     Help,
     Version,
     MissingArgument,
+    InvalidArgument,
     UnknownFlag,
 }
 
+impl CliExit {
+    fn exit(self) -> ! {
+        match self {
+            CliExit::Help | CliExit::Version => std::process::exit(0),
+            CliExit::MissingArgument | CliExit::InvalidArgument => std::process::exit(2),
+            CliExit::UnknownFlag => std::process::exit(1),
+        }
+    }
+}
+
+/// Subcommand of the runner.
+#[derive(Debug)]
+pub enum Command {
+    /// `go-runner test`, runs the benchmarks.
+    Test(Cli),
+    /// `go-runner compare`, compares the results of two runs.
+    Compare(CompareCli),
+}
+
+impl Command {
+    pub fn parse() -> Self {
+        Self::parse_args(std::env::args().skip(1)).unwrap_or_else(|exit| exit.exit())
+    }
+
+    fn parse_args(args: impl Iterator<Item = String>) -> Result<Self, CliExit> {
+        let mut args = args.peekable();
+        if args.peek().map(String::as_str) == Some("compare") {
+            args.next();
+            return CompareCli::parse_args(args).map(Command::Compare);
+        }
+        Cli::parse_args(args).map(Command::Test)
+    }
+}
+
+/// Arguments of `go-runner compare`, see [`crate::results::compare`].
+#[derive(Debug, PartialEq)]
+pub struct CompareCli {
+    /// Profile (or `results`) folders of both runs
+    pub baseline: PathBuf,
+    pub candidate: PathBuf,
+
+    pub options: CompareOptions,
+}
+
+impl CompareCli {
+    fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Self, CliExit> {
+        let mut options = CompareOptions::default();
+        let mut folders = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!(
+                        "\
+Compare the results of two runs of the Codspeed Go Benchmark Runner
+
+USAGE:
+    go-runner compare [OPTIONS] <BASELINE> <CANDIDATE>
+
+ARGS:
+    <BASELINE>           Profile folder (or its 'results' folder) of the reference run
+    <CANDIDATE>          Profile folder (or its 'results' folder) of the run to check
+
+OPTIONS:
+    --threshold <f>      Relative change of the mean below which a benchmark is unchanged
+                         (defaults to 0.05)
+    --significance-level <f> Maximum p-value of a significant change (defaults to 0.05)
+    -h, --help           Print help information
+
+Exits with code {REGRESSION_EXIT_CODE} when some benchmarks regressed or failed in the candidate run."
+                    );
+                    return Err(CliExit::Help);
+                }
+                flag @ ("--threshold" | "--significance-level") => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `{flag}` requires a number");
+                        CliExit::MissingArgument
+                    })?;
+                    parse_compare_flag(&mut options, flag, &value)?;
+                }
+                s if s.starts_with("--threshold=") || s.starts_with("--significance-level=") => {
+                    let (flag, value) = s.split_once('=').unwrap();
+                    parse_compare_flag(&mut options, flag, value)?;
+                }
+                s if s.starts_with('-') => {
+                    eprintln!("error: unknown flag '{s}' for `go-runner compare`");
+                    return Err(CliExit::UnknownFlag);
+                }
+                _ => folders.push(PathBuf::from(arg)),
+            }
+        }
+
+        let [baseline, candidate] = <[PathBuf; 2]>::try_from(folders).map_err(|_| {
+            eprintln!("error: `go-runner compare` requires a baseline and a candidate folder");
+            CliExit::MissingArgument
+        })?;
+        Ok(Self {
+            baseline,
+            candidate,
+            options,
+        })
+    }
+}
+
+fn parse_compare_flag(
+    options: &mut CompareOptions,
+    flag: &str,
+    value: &str,
+) -> Result<(), CliExit> {
+    let number: f64 = parse_number(flag, value)?;
+    let valid = match flag {
+        "--threshold" => number >= 0.0 && number.is_finite(),
+        "--significance-level" => number > 0.0 && number < 1.0,
+        _ => unreachable!("unknown compare flag: {flag}"),
+    };
+    if !valid {
+        eprintln!("error: invalid value '{value}' for `{flag}`");
+        return Err(CliExit::InvalidArgument);
+    }
+    match flag {
+        "--threshold" => options.threshold = number,
+        _ => options.significance_level = number,
+    }
+    Ok(())
+}
+
+/// The directory that benchmark URIs are relative to.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum UriRoot {
+    /// Root of the git repository (a `.git` directory, or a `.git` file for worktrees).
+    /// Submodules are part of their parent repository. Falls back to the module root if the
+    /// project isn't in a git repository.
+    #[default]
+    Git,
+    /// Root of the Go module containing the benchmark.
+    Module,
+    /// Explicit directory, relative paths are resolved from the project directory.
+    Path(PathBuf),
+}
+
+impl UriRoot {
+    /// Parses `--uri-root`. Anything but `git` and `module` is a path, which is only checked once
+    /// it's resolved from the project directory, see `resolve_uri_root`.
+    fn parse(value: &str) -> Result<Self, CliExit> {
+        match value {
+            "git" => Ok(UriRoot::Git),
+            "module" => Ok(UriRoot::Module),
+            "" => {
+                eprintln!("error: `--uri-root` requires 'git', 'module' or a directory");
+                Err(CliExit::InvalidArgument)
+            }
+            path => Ok(UriRoot::Path(PathBuf::from(path))),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Cli {
     /// Run only benchmarks matching regexp
@@ -16,6 +178,35 @@ pub struct Cli {
 
     /// Package patterns to run benchmarks for
     pub packages: Vec<String>,
+
+    /// Receive the instrument hooks events in the runner, instead of the CodSpeed runner
+    pub local_hooks: bool,
+
+    /// The directory that benchmark URIs are relative to
+    pub uri_root: UriRoot,
+
+    /// Config file, see [`crate::config::Config`]
+    pub config: Option<PathBuf>,
+
+    /// Overrides of the round strategy from the config file
+    pub min_rounds: Option<u64>,
+    pub warmup_fraction: Option<f64>,
+    pub max_time_multiplier: Option<f64>,
+
+    /// Don't fail the run when some raw results couldn't be parsed
+    pub allow_invalid_raw_results: bool,
+
+    /// Keep and archive the raw results, optionally compressed
+    pub keep_raw_results: bool,
+    pub compress_raw_results: bool,
+
+    /// Override of the raw results transport from the config file
+    pub raw_results_transport: Option<RawResultsTransport>,
+
+    /// Overrides of the statistics settings from the config file
+    pub confidence_level: Option<f64>,
+    pub bootstrap_resamples: Option<u32>,
+    pub outlier_policy: Option<OutlierPolicy>,
 }
 
 impl Default for Cli {
@@ -24,19 +215,26 @@ impl Default for Cli {
             bench: ".".into(),
             benchtime: "3s".into(),
             packages: vec!["./...".into()],
+            local_hooks: false,
+            uri_root: UriRoot::default(),
+            config: None,
+            min_rounds: None,
+            warmup_fraction: None,
+            max_time_multiplier: None,
+            allow_invalid_raw_results: false,
+            keep_raw_results: false,
+            compress_raw_results: false,
+            raw_results_transport: None,
+            confidence_level: None,
+            bootstrap_resamples: None,
+            outlier_policy: None,
         }
     }
 }
 
 impl Cli {
     pub fn parse() -> Self {
-        match Self::parse_args(std::env::args().skip(1)) {
-            Ok(cli) => cli,
-            Err(CliExit::Help) => std::process::exit(0),
-            Err(CliExit::Version) => std::process::exit(0),
-            Err(CliExit::MissingArgument) => std::process::exit(2),
-            Err(CliExit::UnknownFlag) => std::process::exit(1),
-        }
+        Self::parse_args(std::env::args().skip(1)).unwrap_or_else(|exit| exit.exit())
     }
 
     /// Parses the command-line arguments into a Cli instance.
@@ -51,7 +249,7 @@ impl Cli {
     fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Self, CliExit> {
         let mut instance = Self::default();
 
-        // We currently only support the `test` subcommand.
+        // `compare` is handled by `Command`, which parses the other subcommands.
         let cmd = args.next();
         assert!(
             cmd == Some("test".to_string()),
@@ -67,16 +265,38 @@ The Codspeed Go Benchmark Runner
 
 USAGE:
     go-runner test [OPTIONS] [PACKAGES...]
+    go-runner compare [OPTIONS] <BASELINE> <CANDIDATE> (see go-runner compare --help)
 
 OPTIONS:
     -bench <pattern>     Run only benchmarks matching regexp (defaults to '.')
     -benchtime <duration> Run each benchmark for duration d (defaults to '3s')
     --dry-run            Build benchmarks but don't execute them
+    --local-hooks        Receive and validate the instrument hooks events locally
+                         (when not running with the CodSpeed runner)
+    --uri-root <root>    Compute benchmark URIs relative to 'git' (git root, the default),
+                         'module' (Go module root) or a directory, relative to the project
+    --config <path>      Read the settings from a JSON config file
+    --min-rounds <n>     Number of measured rounds per benchmark (defaults to 100)
+    --warmup-fraction <f> Duration of the warmup, relative to the benchtime (defaults to 0.1)
+    --max-time-multiplier <f> Stop a benchmark after benchtime * f (defaults to 3)
+    --allow-invalid-raw-results Don't fail the run when some raw results couldn't be parsed
+    --keep-raw-results   Keep the raw results and archive them next to the results
+    --compress-raw-results Same as --keep-raw-results, with a compressed archive
+    --raw-results-transport <transport> Write the raw results to a 'log' per process (the
+                         default) or to 'files' per benchmark
+    --confidence-level <f> Confidence level of the confidence intervals (defaults to 0.95)
+    --bootstrap-resamples <n> Bootstrap resamples of the confidence intervals (defaults to
+                         1000, 0 disables them)
+    --outlier-policy <policy> Also report stats without outliers: 'iqr', 'mad',
+                         'winsorize' or 'none' (the default)
     -h, --help           Print help information
     -V, --version        Print version information
 
 SUPPORTED FLAGS:
-    -bench, -benchtime, --dry-run
+    -bench, -benchtime, --dry-run, --local-hooks, --uri-root, --config, --min-rounds,
+    --warmup-fraction, --max-time-multiplier, --allow-invalid-raw-results, --keep-raw-results,
+    --compress-raw-results, --raw-results-transport, --confidence-level, --bootstrap-resamples,
+    --outlier-policy
 
 UNSUPPORTED FLAGS (will be warned about):
     -benchmem, -count, -cpu, -cpuprofile, -memprofile, -trace, etc."
@@ -105,6 +325,87 @@ UNSUPPORTED FLAGS (will be warned about):
                 s if s.starts_with("-benchtime=") => {
                     instance.benchtime = s.split_once('=').unwrap().1.to_string();
                 }
+                "--local-hooks" => {
+                    instance.local_hooks = true;
+                }
+                "--allow-invalid-raw-results" => {
+                    instance.allow_invalid_raw_results = true;
+                }
+                "--keep-raw-results" => {
+                    instance.keep_raw_results = true;
+                }
+                "--compress-raw-results" => {
+                    instance.compress_raw_results = true;
+                }
+                "--raw-results-transport" => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `--raw-results-transport` requires 'log' or 'files'");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.raw_results_transport = Some(parse_transport(&value)?);
+                }
+                s if s.starts_with("--raw-results-transport=") => {
+                    let value = s.split_once('=').unwrap().1;
+                    instance.raw_results_transport = Some(parse_transport(value)?);
+                }
+                "--uri-root" => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `--uri-root` requires 'git', 'module' or a directory");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.uri_root = UriRoot::parse(&value)?;
+                }
+                s if s.starts_with("--uri-root=") => {
+                    instance.uri_root = UriRoot::parse(s.split_once('=').unwrap().1)?;
+                }
+                "--config" => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `--config` requires a path");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.config = Some(PathBuf::from(value));
+                }
+                s if s.starts_with("--config=") => {
+                    instance.config = Some(PathBuf::from(s.split_once('=').unwrap().1));
+                }
+                flag @ ("--min-rounds" | "--warmup-fraction" | "--max-time-multiplier") => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `{flag}` requires a number");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.parse_round_strategy_flag(flag, &value)?;
+                }
+                s if s.starts_with("--min-rounds=")
+                    || s.starts_with("--warmup-fraction=")
+                    || s.starts_with("--max-time-multiplier=") =>
+                {
+                    let (flag, value) = s.split_once('=').unwrap();
+                    instance.parse_round_strategy_flag(flag, value)?;
+                }
+                flag @ ("--confidence-level" | "--bootstrap-resamples") => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `{flag}` requires a number");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.parse_stats_flag(flag, &value)?;
+                }
+                s if s.starts_with("--confidence-level=")
+                    || s.starts_with("--bootstrap-resamples=") =>
+                {
+                    let (flag, value) = s.split_once('=').unwrap();
+                    instance.parse_stats_flag(flag, value)?;
+                }
+                "--outlier-policy" => {
+                    let value = args.next().ok_or_else(|| {
+                        eprintln!("error: `--outlier-policy` requires a policy");
+                        CliExit::MissingArgument
+                    })?;
+                    instance.outlier_policy = Some(parse_outlier_policy(&value)?);
+                }
+                s if s.starts_with("--outlier-policy=") => {
+                    let value = s.split_once('=').unwrap().1;
+                    instance.outlier_policy = Some(parse_outlier_policy(value)?);
+                }
                 s if s.starts_with('-') => {
                     eprintln!(
                         "warning: flag '{s}' is not supported by CodSpeed Go runner, ignoring"
@@ -123,6 +424,48 @@ UNSUPPORTED FLAGS (will be warned about):
         }
         Ok(instance)
     }
+
+    fn parse_round_strategy_flag(&mut self, flag: &str, value: &str) -> Result<(), CliExit> {
+        match flag {
+            "--min-rounds" => self.min_rounds = Some(parse_number(flag, value)?),
+            "--warmup-fraction" => self.warmup_fraction = Some(parse_number(flag, value)?),
+            "--max-time-multiplier" => self.max_time_multiplier = Some(parse_number(flag, value)?),
+            _ => unreachable!("unknown round strategy flag: {flag}"),
+        }
+        Ok(())
+    }
+
+    fn parse_stats_flag(&mut self, flag: &str, value: &str) -> Result<(), CliExit> {
+        match flag {
+            "--confidence-level" => self.confidence_level = Some(parse_number(flag, value)?),
+            "--bootstrap-resamples" => self.bootstrap_resamples = Some(parse_number(flag, value)?),
+            _ => unreachable!("unknown stats flag: {flag}"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, CliExit> {
+    value.parse().map_err(|_| {
+        eprintln!("error: invalid value '{value}' for `{flag}`");
+        CliExit::InvalidArgument
+    })
+}
+
+fn parse_transport(value: &str) -> Result<RawResultsTransport, CliExit> {
+    RawResultsTransport::parse(value).ok_or_else(|| {
+        eprintln!("error: invalid value '{value}' for `--raw-results-transport`, expected 'log' or 'files'");
+        CliExit::InvalidArgument
+    })
+}
+
+fn parse_outlier_policy(value: &str) -> Result<OutlierPolicy, CliExit> {
+    OutlierPolicy::parse(value).ok_or_else(|| {
+        eprintln!(
+            "error: invalid value '{value}' for `--outlier-policy`, expected 'none', 'iqr', 'mad' or 'winsorize'"
+        );
+        CliExit::InvalidArgument
+    })
 }
 
 #[cfg(test)]
@@ -147,6 +490,50 @@ mod tests {
         assert_eq!(cli.bench, ".");
         assert_eq!(cli.benchtime, Cli::default().benchtime);
         assert_eq!(cli.packages, Cli::default().packages);
+        assert!(!cli.local_hooks);
+        assert!(!cli.allow_invalid_raw_results);
+    }
+
+    #[test]
+    fn test_cli_parse_with_allow_invalid_raw_results_flag() {
+        let cli = str_to_iter("go-runner test --allow-invalid-raw-results ./pkg").unwrap();
+        assert!(cli.allow_invalid_raw_results);
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
+    }
+
+    #[test]
+    fn test_cli_parse_with_raw_results_archive_flags() {
+        let cli = str_to_iter("go-runner test --keep-raw-results").unwrap();
+        assert!(cli.keep_raw_results);
+        assert!(!cli.compress_raw_results);
+
+        let cli = str_to_iter("go-runner test --compress-raw-results").unwrap();
+        assert!(cli.compress_raw_results);
+    }
+
+    #[test]
+    fn test_cli_parse_with_raw_results_transport_flag() {
+        let cli = str_to_iter("go-runner test").unwrap();
+        assert_eq!(cli.raw_results_transport, None);
+
+        let cli = str_to_iter("go-runner test --raw-results-transport files ./pkg").unwrap();
+        assert_eq!(cli.raw_results_transport, Some(RawResultsTransport::Files));
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
+
+        let cli = str_to_iter("go-runner test --raw-results-transport=log").unwrap();
+        assert_eq!(cli.raw_results_transport, Some(RawResultsTransport::Log));
+
+        assert!(matches!(
+            str_to_iter("go-runner test --raw-results-transport socket"),
+            Err(CliExit::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_with_local_hooks_flag() {
+        let cli = str_to_iter("go-runner test --local-hooks -bench=. ./pkg").unwrap();
+        assert!(cli.local_hooks);
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
     }
 
     #[test]
@@ -185,6 +572,131 @@ mod tests {
         assert_eq!(cli.packages, vec!["./pkg".to_string()]);
     }
 
+    #[test]
+    fn test_cli_parse_with_stats_flags() {
+        let cli =
+            str_to_iter("go-runner test --confidence-level 0.99 --bootstrap-resamples=0").unwrap();
+        assert_eq!(cli.confidence_level, Some(0.99));
+        assert_eq!(cli.bootstrap_resamples, Some(0));
+
+        let result = str_to_iter("go-runner test --bootstrap-resamples -1");
+        assert!(matches!(result, Err(CliExit::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_cli_parse_with_outlier_policy_flag() {
+        let cli = str_to_iter("go-runner test").unwrap();
+        assert_eq!(cli.outlier_policy, None);
+
+        let cli = str_to_iter("go-runner test --outlier-policy iqr").unwrap();
+        assert_eq!(cli.outlier_policy, Some(OutlierPolicy::Iqr));
+
+        let cli = str_to_iter("go-runner test --outlier-policy=winsorize ./pkg").unwrap();
+        assert_eq!(cli.outlier_policy, Some(OutlierPolicy::Winsorize));
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
+
+        let result = str_to_iter("go-runner test --outlier-policy trim");
+        assert!(matches!(result, Err(CliExit::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_cli_parse_with_uri_root_flag() {
+        let cli = str_to_iter("go-runner test").unwrap();
+        assert_eq!(cli.uri_root, UriRoot::Git);
+
+        let cli = str_to_iter("go-runner test --uri-root module").unwrap();
+        assert_eq!(cli.uri_root, UriRoot::Module);
+
+        let cli = str_to_iter("go-runner test --uri-root=git").unwrap();
+        assert_eq!(cli.uri_root, UriRoot::Git);
+
+        let cli = str_to_iter("go-runner test --uri-root=src ./pkg").unwrap();
+        assert_eq!(cli.uri_root, UriRoot::Path(PathBuf::from("src")));
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
+
+        // Checked once resolved from the project directory
+        let cli = str_to_iter("go-runner test --uri-root=../missing-dir").unwrap();
+        assert_eq!(cli.uri_root, UriRoot::Path(PathBuf::from("../missing-dir")));
+
+        let result = str_to_iter("go-runner test --uri-root=");
+        assert!(matches!(result, Err(CliExit::InvalidArgument)));
+    }
+
+    #[test]
+    fn test_cli_parse_with_round_strategy_flags() {
+        let cli = str_to_iter(
+            "go-runner test --config codspeed.json --min-rounds 500 --warmup-fraction=0.2 \
+             --max-time-multiplier 5 ./pkg",
+        )
+        .unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("codspeed.json")));
+        assert_eq!(cli.min_rounds, Some(500));
+        assert_eq!(cli.warmup_fraction, Some(0.2));
+        assert_eq!(cli.max_time_multiplier, Some(5.0));
+        assert_eq!(cli.packages, vec!["./pkg".to_string()]);
+
+        let result = str_to_iter("go-runner test --min-rounds many");
+        assert!(matches!(result, Err(CliExit::InvalidArgument)));
+
+        let result = str_to_iter("go-runner test --warmup-fraction");
+        assert!(matches!(result, Err(CliExit::MissingArgument)));
+    }
+
+    fn parse_command(cmd: &str) -> Result<Command, CliExit> {
+        Command::parse_args(cmd.split_whitespace().skip(1).map(String::from))
+    }
+
+    #[test]
+    fn test_command_parse() {
+        let command = parse_command("go-runner test -bench=Foo ./pkg").unwrap();
+        assert!(matches!(command, Command::Test(cli) if cli.bench == "Foo"));
+
+        let Command::Compare(compare) = parse_command("go-runner compare base head").unwrap()
+        else {
+            panic!("expected the compare command");
+        };
+        assert_eq!(
+            compare,
+            CompareCli {
+                baseline: PathBuf::from("base"),
+                candidate: PathBuf::from("head"),
+                options: CompareOptions::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_compare_cli_parse_flags() {
+        let Command::Compare(compare) =
+            parse_command("go-runner compare --threshold 0.1 base --significance-level=0.01 head")
+                .unwrap()
+        else {
+            panic!("expected the compare command");
+        };
+        assert_eq!(compare.baseline, PathBuf::from("base"));
+        assert_eq!(compare.candidate, PathBuf::from("head"));
+        assert_eq!(compare.options.threshold, 0.1);
+        assert_eq!(compare.options.significance_level, 0.01);
+
+        for (cmd, expected) in [
+            ("go-runner compare base", CliExit::MissingArgument),
+            ("go-runner compare a b c", CliExit::MissingArgument),
+            ("go-runner compare --threshold", CliExit::MissingArgument),
+            (
+                "go-runner compare --threshold -1 a b",
+                CliExit::InvalidArgument,
+            ),
+            (
+                "go-runner compare --significance-level 1 a b",
+                CliExit::InvalidArgument,
+            ),
+            ("go-runner compare --bench a b", CliExit::UnknownFlag),
+            ("go-runner compare --help", CliExit::Help),
+        ] {
+            assert_eq!(parse_command(cmd).err(), Some(expected), "{cmd}");
+        }
+    }
+
     #[test]
     fn test_cli_parse_help_flag() {
         let result = str_to_iter("go-runner test -h");
@@ -211,6 +723,9 @@ mod tests {
         let result = str_to_iter("go-runner test -benchtime");
         assert!(matches!(result, Err(CliExit::MissingArgument)));
 
+        let result = str_to_iter("go-runner test --uri-root");
+        assert!(matches!(result, Err(CliExit::MissingArgument)));
+
         // Unknown flags now generate warnings but don't cause errors
         let result = str_to_iter("go-runner test -unknown");
         assert!(result.is_ok());
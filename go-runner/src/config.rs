@@ -0,0 +1,387 @@
+//! Settings that can be set in a config file (`--config <path>`) and overridden from the CLI.
+//!
+//! The config file uses JSON, e.g.:
+//! ```json
+//! {
+//!   "rounds": { "min_rounds": 500, "warmup_fraction": 0.2, "max_time_multiplier": 5 },
+//!   "results": {
+//!     "allow_invalid_raw_results": true,
+//!     "keep_raw_results": true,
+//!     "stats": { "confidence_level": 0.99 }
+//!   }
+//! }
+//! ```
+
+use crate::cli::Cli;
+use crate::prelude::*;
+use crate::results::schema::OutlierPolicy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rounds: RoundStrategy,
+    pub results: ResultsConfig,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {path:?}"))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {path:?}"))
+    }
+
+    /// Returns the config from the config file, overridden by the CLI flags.
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        let mut config = match &cli.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        config.rounds.apply_cli(cli);
+        config.rounds.validate()?;
+        config.results.apply_cli(cli);
+        config.results.stats.validate()?;
+
+        Ok(config)
+    }
+}
+
+/// How the benchmarks are split into measured rounds by the overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoundStrategy {
+    /// Number of rounds, unless the benchmark is too slow to run that many iterations.
+    pub min_rounds: u64,
+    /// Duration of the warmup phase, relative to the benchtime.
+    pub warmup_fraction: f64,
+    /// A benchmark is stopped once it ran for `benchtime * max_time_multiplier`, including the
+    /// time spent outside of the measured sections.
+    pub max_time_multiplier: f64,
+}
+
+impl Default for RoundStrategy {
+    fn default() -> Self {
+        Self {
+            min_rounds: 100,
+            warmup_fraction: 0.1,
+            max_time_multiplier: 3.0,
+        }
+    }
+}
+
+impl RoundStrategy {
+    /// Returns the round strategy from the config file, overridden by the CLI flags.
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        Config::from_cli(cli).map(|config| config.rounds)
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(min_rounds) = cli.min_rounds {
+            self.min_rounds = min_rounds;
+        }
+        if let Some(warmup_fraction) = cli.warmup_fraction {
+            self.warmup_fraction = warmup_fraction;
+        }
+        if let Some(max_time_multiplier) = cli.max_time_multiplier {
+            self.max_time_multiplier = max_time_multiplier;
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.min_rounds == 0 || self.min_rounds > i32::MAX as u64 {
+            bail!("min_rounds must be between 1 and {}", i32::MAX);
+        }
+        if !(0.0..=1.0).contains(&self.warmup_fraction) {
+            bail!("warmup_fraction must be between 0 and 1");
+        }
+        if !self.max_time_multiplier.is_finite() || self.max_time_multiplier < 1.0 {
+            bail!("max_time_multiplier must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+/// How the raw results written by the overlay are collected after the run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResultsConfig {
+    /// Don't fail the run when some raw results couldn't be parsed. They're still reported and
+    /// moved to the quarantine folder.
+    pub allow_invalid_raw_results: bool,
+    /// Move the raw results to an archive next to the results once parsed, instead of deleting
+    /// them (see [`crate::results::archive`]).
+    pub keep_raw_results: bool,
+    /// Compress the archived raw results. Implies `keep_raw_results`.
+    pub compress_raw_results: bool,
+    /// How the overlay writes the raw results.
+    pub raw_results_transport: RawResultsTransport,
+    /// How the statistics of the benchmarks are computed from their rounds.
+    pub stats: StatsConfig,
+}
+
+/// Settings of the statistics computed from the rounds, see [`crate::results::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Confidence level of the confidence intervals of the mean and median.
+    pub confidence_level: f64,
+    /// Number of bootstrap resamples used to compute the confidence intervals, 0 disables them.
+    pub bootstrap_resamples: u32,
+    /// Seed of the bootstrap, so that the same rounds always give the same confidence intervals.
+    pub bootstrap_seed: u64,
+    /// How the outlier rounds are handled in the filtered stats, which are reported next to the
+    /// stats of all the rounds.
+    pub outlier_policy: OutlierPolicy,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            confidence_level: 0.95,
+            bootstrap_resamples: 1000,
+            bootstrap_seed: 0,
+            outlier_policy: OutlierPolicy::None,
+        }
+    }
+}
+
+impl StatsConfig {
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(confidence_level) = cli.confidence_level {
+            self.confidence_level = confidence_level;
+        }
+        if let Some(bootstrap_resamples) = cli.bootstrap_resamples {
+            self.bootstrap_resamples = bootstrap_resamples;
+        }
+        if let Some(outlier_policy) = cli.outlier_policy {
+            self.outlier_policy = outlier_policy;
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.confidence_level > 0.0 && self.confidence_level < 1.0) {
+            bail!("confidence_level must be between 0 and 1 (exclusive)");
+        }
+        Ok(())
+    }
+}
+
+/// How the overlay writes the raw results to `<profile dir>/raw_results/`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawResultsTransport {
+    /// Appended to a log per process (`<pid>.log`), see [`crate::results::raw_log`].
+    #[default]
+    Log,
+    /// A file per benchmark (`<random>.json`). The overlay also falls back to it when the log
+    /// can't be written.
+    Files,
+}
+
+impl RawResultsTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RawResultsTransport::Log => "log",
+            RawResultsTransport::Files => "files",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "log" => Some(RawResultsTransport::Log),
+            "files" => Some(RawResultsTransport::Files),
+            _ => None,
+        }
+    }
+}
+
+impl ResultsConfig {
+    fn apply_cli(&mut self, cli: &Cli) {
+        if cli.allow_invalid_raw_results {
+            self.allow_invalid_raw_results = true;
+        }
+        if cli.keep_raw_results {
+            self.keep_raw_results = true;
+        }
+        if cli.compress_raw_results {
+            self.compress_raw_results = true;
+        }
+        if self.compress_raw_results {
+            self.keep_raw_results = true;
+        }
+        if let Some(transport) = cli.raw_results_transport {
+            self.raw_results_transport = transport;
+        }
+        self.stats.apply_cli(cli);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_round_strategy_defaults() {
+        let strategy = RoundStrategy::from_cli(&Cli::default()).unwrap();
+        assert_eq!(strategy, RoundStrategy::default());
+    }
+
+    #[test]
+    fn test_round_strategy_from_config_file_and_cli() {
+        let config = NamedTempFile::new().unwrap();
+        std::fs::write(
+            config.path(),
+            r#"{ "rounds": { "min_rounds": 500, "warmup_fraction": 0.2 } }"#,
+        )
+        .unwrap();
+
+        let cli = Cli {
+            config: Some(config.path().to_path_buf()),
+            warmup_fraction: Some(0.5),
+            ..Default::default()
+        };
+        let strategy = RoundStrategy::from_cli(&cli).unwrap();
+        assert_eq!(
+            strategy,
+            RoundStrategy {
+                min_rounds: 500,
+                warmup_fraction: 0.5,
+                max_time_multiplier: 3.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_strategy_invalid() {
+        for cli in [
+            Cli {
+                min_rounds: Some(0),
+                ..Default::default()
+            },
+            Cli {
+                warmup_fraction: Some(1.5),
+                ..Default::default()
+            },
+            Cli {
+                max_time_multiplier: Some(0.5),
+                ..Default::default()
+            },
+        ] {
+            assert!(RoundStrategy::from_cli(&cli).is_err(), "{cli:?}");
+        }
+    }
+
+    #[test]
+    fn test_results_config_from_config_file_and_cli() {
+        let config = NamedTempFile::new().unwrap();
+        std::fs::write(
+            config.path(),
+            r#"{ "results": { "allow_invalid_raw_results": true } }"#,
+        )
+        .unwrap();
+        let cli = Cli {
+            config: Some(config.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert!(
+            Config::from_cli(&cli)
+                .unwrap()
+                .results
+                .allow_invalid_raw_results
+        );
+
+        assert!(
+            !Config::from_cli(&Cli::default())
+                .unwrap()
+                .results
+                .allow_invalid_raw_results
+        );
+        let cli = Cli {
+            allow_invalid_raw_results: true,
+            ..Default::default()
+        };
+        assert!(
+            Config::from_cli(&cli)
+                .unwrap()
+                .results
+                .allow_invalid_raw_results
+        );
+    }
+
+    #[test]
+    fn test_compress_raw_results_implies_keep() {
+        let cli = Cli {
+            compress_raw_results: true,
+            ..Default::default()
+        };
+        let results = Config::from_cli(&cli).unwrap().results;
+        assert!(results.keep_raw_results);
+        assert!(results.compress_raw_results);
+    }
+
+    #[test]
+    fn test_raw_results_transport_from_config_file_and_cli() {
+        assert_eq!(
+            Config::from_cli(&Cli::default())
+                .unwrap()
+                .results
+                .raw_results_transport,
+            RawResultsTransport::Log
+        );
+
+        let config = NamedTempFile::new().unwrap();
+        std::fs::write(
+            config.path(),
+            r#"{ "results": { "raw_results_transport": "files" } }"#,
+        )
+        .unwrap();
+        let mut cli = Cli {
+            config: Some(config.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Config::from_cli(&cli).unwrap().results;
+        assert_eq!(results.raw_results_transport, RawResultsTransport::Files);
+
+        cli.raw_results_transport = Some(RawResultsTransport::Log);
+        let results = Config::from_cli(&cli).unwrap().results;
+        assert_eq!(results.raw_results_transport, RawResultsTransport::Log);
+    }
+
+    #[test]
+    fn test_stats_config_from_config_file_and_cli() {
+        let config = NamedTempFile::new().unwrap();
+        std::fs::write(
+            config.path(),
+            r#"{ "results": { "stats": { "confidence_level": 0.99, "bootstrap_seed": 7, "outlier_policy": "mad" } } }"#,
+        )
+        .unwrap();
+        let mut cli = Cli {
+            config: Some(config.path().to_path_buf()),
+            bootstrap_resamples: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_cli(&cli).unwrap().results.stats,
+            StatsConfig {
+                confidence_level: 0.99,
+                bootstrap_resamples: 0,
+                bootstrap_seed: 7,
+                outlier_policy: OutlierPolicy::Mad,
+            }
+        );
+
+        cli.confidence_level = Some(1.0);
+        assert!(Config::from_cli(&cli).is_err());
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_fields() {
+        let config = NamedTempFile::new().unwrap();
+        std::fs::write(config.path(), r#"{ "rounds": { "min_round": 500 } }"#).unwrap();
+        assert!(Config::load(config.path()).is_err());
+    }
+}
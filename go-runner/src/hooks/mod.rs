@@ -0,0 +1,271 @@
+//! Runner side of the instrument-hooks protocol.
+//!
+//! When the benchmarks are executed with the CodSpeed runner, it receives the commands sent through
+//! the instrument hooks. Otherwise, the [`HooksServer`] can receive them locally, so that they can be
+//! validated and attached to the results.
+
+use crate::prelude::*;
+use itertools::Itertools;
+use protocol::MarkerType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+pub mod protocol;
+mod server;
+
+pub use server::HooksServer;
+
+/// Environment sections sent by a benchmark process (section name -> key -> value).
+pub type Environment = BTreeMap<String, BTreeMap<String, String>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Integration {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutedBenchmark {
+    pub pid: i32,
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub pid: i32,
+    pub marker: MarkerType,
+}
+
+/// Events received from the instrument hooks of all the benchmark processes.
+#[derive(Debug, Default)]
+pub struct HooksEvents {
+    pub protocol_version: Option<u64>,
+    pub integration: Option<Integration>,
+    pub executed_benchmarks: Vec<ExecutedBenchmark>,
+    pub markers: Vec<Marker>,
+    /// Environments written by the benchmark processes, by pid.
+    pub environments: BTreeMap<i32, Environment>,
+}
+
+impl HooksEvents {
+    /// Checks that every benchmark start marker has a matching end marker in the same process.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        let markers_by_pid = self.markers.iter().into_group_map_by(|marker| marker.pid);
+        for (pid, markers) in markers_by_pid.into_iter().sorted_by_key(|(pid, _)| *pid) {
+            let mut start = None;
+            for marker in markers {
+                match (marker.marker, start) {
+                    (MarkerType::BenchmarkStart(ts), None) => start = Some(ts),
+                    (MarkerType::BenchmarkStart(ts), Some(prev_ts)) => {
+                        errors.push(format!(
+                            "pid {pid}: benchmark start at {prev_ts} has no matching end before the next start at {ts}"
+                        ));
+                        start = Some(ts);
+                    }
+                    (MarkerType::BenchmarkEnd(ts), Some(start_ts)) => {
+                        if ts < start_ts {
+                            errors.push(format!(
+                                "pid {pid}: benchmark end at {ts} happened before its start at {start_ts}"
+                            ));
+                        }
+                        start = None;
+                    }
+                    (MarkerType::BenchmarkEnd(ts), None) => {
+                        errors.push(format!(
+                            "pid {pid}: benchmark end at {ts} has no matching start"
+                        ));
+                    }
+                    (MarkerType::SampleStart(_) | MarkerType::SampleEnd(_), _) => {}
+                }
+            }
+
+            if let Some(ts) = start {
+                errors.push(format!(
+                    "pid {pid}: benchmark start at {ts} has no matching end"
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!("Invalid instrument hooks markers:\n{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// Returns the data sent by the given benchmark process.
+    pub fn for_pid(&self, pid: u32) -> InstrumentHooksData {
+        let matches_pid = |other: i32| u32::try_from(other).ok() == Some(pid);
+
+        InstrumentHooksData {
+            integration: self.integration.clone(),
+            environment: self
+                .environments
+                .iter()
+                .find(|(other, _)| matches_pid(**other))
+                .map(|(_, environment)| environment.clone())
+                .unwrap_or_default(),
+            executed_benchmarks: self
+                .executed_benchmarks
+                .iter()
+                .filter(|bench| matches_pid(bench.pid))
+                .map(|bench| bench.uri.clone())
+                .collect(),
+            markers: self
+                .markers
+                .iter()
+                .filter(|marker| matches_pid(marker.pid))
+                .map(|marker| marker.marker)
+                .collect(),
+        }
+    }
+}
+
+/// Data received from the instrument hooks of a single benchmark process.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct InstrumentHooksData {
+    pub integration: Option<Integration>,
+    pub environment: Environment,
+    pub executed_benchmarks: Vec<String>,
+    pub markers: Vec<MarkerType>,
+}
+
+/// Environment file written by the overlay of a benchmark process, only with `--local-hooks`.
+pub fn environment_path(profile_dir: &Path, pid: u32) -> PathBuf {
+    profile_dir.join("environment").join(format!("{pid}.json"))
+}
+
+/// Loads the environment written by the overlay to `<profile dir>/environment/<pid>.json`.
+pub fn load_environment(profile_dir: &Path, pid: u32) -> Environment {
+    let path = environment_path(profile_dir, pid);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) => {
+            debug!("No environment found at {path:?}: {error}");
+            return Environment::default();
+        }
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|error| {
+        warn!("Failed to parse environment {path:?}: {error}");
+        Environment::default()
+    })
+}
+
+/// Loads the environments written by all the benchmark processes, by pid.
+pub fn load_environments(profile_dir: &Path) -> BTreeMap<u32, Environment> {
+    let Ok(entries) = std::fs::read_dir(profile_dir.join("environment")) else {
+        return BTreeMap::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            path.file_stem()?.to_str()?.parse::<u32>().ok()
+        })
+        .map(|pid| (pid, load_environment(profile_dir, pid)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(markers: &[(i32, MarkerType)]) -> HooksEvents {
+        HooksEvents {
+            markers: markers
+                .iter()
+                .map(|&(pid, marker)| Marker { pid, marker })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_matching_markers() {
+        let events = events(&[
+            (1, MarkerType::BenchmarkStart(10)),
+            (2, MarkerType::BenchmarkStart(11)),
+            (1, MarkerType::BenchmarkEnd(20)),
+            (2, MarkerType::BenchmarkEnd(21)),
+            (1, MarkerType::BenchmarkStart(30)),
+            (1, MarkerType::BenchmarkEnd(40)),
+        ]);
+        events.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_missing_end() {
+        let error = events(&[
+            (1, MarkerType::BenchmarkStart(10)),
+            (1, MarkerType::BenchmarkEnd(20)),
+            (1, MarkerType::BenchmarkStart(30)),
+        ])
+        .validate()
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("pid 1: benchmark start at 30 has no matching end"));
+    }
+
+    #[test]
+    fn test_validate_invalid_markers() {
+        let error = events(&[
+            (1, MarkerType::BenchmarkEnd(5)),
+            (1, MarkerType::BenchmarkStart(10)),
+            (1, MarkerType::BenchmarkStart(20)),
+            (1, MarkerType::BenchmarkEnd(15)),
+        ])
+        .validate()
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("end at 5 has no matching start"));
+        assert!(error.contains("start at 10 has no matching end"));
+        assert!(error.contains("end at 15 happened before its start at 20"));
+    }
+
+    #[test]
+    fn test_for_pid() {
+        let mut events = events(&[
+            (1, MarkerType::BenchmarkStart(10)),
+            (2, MarkerType::BenchmarkStart(11)),
+            (1, MarkerType::BenchmarkEnd(20)),
+        ]);
+        events.executed_benchmarks = vec![
+            ExecutedBenchmark {
+                pid: 1,
+                uri: "a_test.go::BenchmarkA".into(),
+            },
+            ExecutedBenchmark {
+                pid: 2,
+                uri: "b_test.go::BenchmarkB".into(),
+            },
+        ];
+        events.environments.insert(
+            1,
+            Environment::from([(
+                "go".into(),
+                BTreeMap::from([("version".into(), "go1.25.0".into())]),
+            )]),
+        );
+
+        let data = events.for_pid(1);
+        assert_eq!(data.environment["go"]["version"], "go1.25.0");
+        assert_eq!(data.executed_benchmarks, vec!["a_test.go::BenchmarkA"]);
+        assert_eq!(
+            data.markers,
+            vec![MarkerType::BenchmarkStart(10), MarkerType::BenchmarkEnd(20)]
+        );
+
+        let data = events.for_pid(2);
+        assert!(data.environment.is_empty());
+    }
+}
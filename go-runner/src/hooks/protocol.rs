@@ -0,0 +1,340 @@
+//! Wire format of the instrument-hooks protocol.
+//!
+//! Every message is a bincode-encoded [`Command`], prefixed with its length (u32, little-endian).
+//!
+//! WARN: Keep in sync with the instrument-hooks library and `overlay/instrument-hooks-purego.go`.
+
+use crate::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// FIFOs of the CodSpeed runner. The [`crate::hooks::HooksServer`] uses its own FIFOs.
+pub const RUNNER_CTL_FIFO: &str = "/tmp/runner.ctl.fifo";
+pub const RUNNER_ACK_FIFO: &str = "/tmp/runner.ack.fifo";
+pub const PROTOCOL_VERSION: u64 = 1;
+
+/// Upper bound for a single message, to avoid allocating huge buffers for corrupted data.
+const MAX_MESSAGE_SIZE: u32 = 1024 * 1024;
+
+/// Commands exchanged between the benchmark process and the runner. The variant order defines
+/// the encoding and must not be changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    ExecutedBenchmark {
+        pid: i32,
+        uri: String,
+    },
+    StartBenchmark,
+    StopBenchmark,
+    Ack,
+    PingPerf,
+    SetIntegration {
+        name: String,
+        version: String,
+    },
+    Err,
+    AddMarker {
+        pid: i32,
+        marker: MarkerType,
+    },
+    SetVersion(u64),
+    /// Environment value of the process. The commands of concurrent benchmark processes are
+    /// interleaved in the FIFO, so every value carries the pid of its process.
+    ///
+    /// This command and [`Command::WriteEnvironment`] are specific to the go-runner: the pure-Go
+    /// hooks only send them with `--local-hooks`, since the C library encodes them differently.
+    SetEnvironment {
+        pid: i32,
+        section: String,
+        key: String,
+        value: String,
+    },
+    /// Sent once all the environment values of the process have been set.
+    WriteEnvironment {
+        pid: i32,
+    },
+}
+
+/// Markers sent by the benchmark process, with the timestamp at which they happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", content = "timestamp", rename_all = "snake_case")]
+pub enum MarkerType {
+    SampleStart(u64),
+    SampleEnd(u64),
+    BenchmarkStart(u64),
+    BenchmarkEnd(u64),
+}
+
+impl Command {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Command::ExecutedBenchmark { pid, uri } => {
+                put_u32(&mut buf, 0);
+                put_i32(&mut buf, *pid);
+                put_str(&mut buf, uri);
+            }
+            Command::StartBenchmark => put_u32(&mut buf, 1),
+            Command::StopBenchmark => put_u32(&mut buf, 2),
+            Command::Ack => put_u32(&mut buf, 3),
+            Command::PingPerf => put_u32(&mut buf, 4),
+            Command::SetIntegration { name, version } => {
+                put_u32(&mut buf, 5);
+                put_str(&mut buf, name);
+                put_str(&mut buf, version);
+            }
+            Command::Err => put_u32(&mut buf, 6),
+            Command::AddMarker { pid, marker } => {
+                put_u32(&mut buf, 7);
+                put_i32(&mut buf, *pid);
+                let (variant, timestamp) = match marker {
+                    MarkerType::SampleStart(ts) => (0, ts),
+                    MarkerType::SampleEnd(ts) => (1, ts),
+                    MarkerType::BenchmarkStart(ts) => (2, ts),
+                    MarkerType::BenchmarkEnd(ts) => (3, ts),
+                };
+                put_u32(&mut buf, variant);
+                put_u64(&mut buf, *timestamp);
+            }
+            Command::SetVersion(version) => {
+                put_u32(&mut buf, 8);
+                put_u64(&mut buf, *version);
+            }
+            Command::SetEnvironment {
+                pid,
+                section,
+                key,
+                value,
+            } => {
+                put_u32(&mut buf, 9);
+                put_i32(&mut buf, *pid);
+                put_str(&mut buf, section);
+                put_str(&mut buf, key);
+                put_str(&mut buf, value);
+            }
+            Command::WriteEnvironment { pid } => {
+                put_u32(&mut buf, 10);
+                put_i32(&mut buf, *pid);
+            }
+        }
+        buf
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        let mut decoder = Decoder { payload };
+        let command = match decoder.u32()? {
+            0 => Command::ExecutedBenchmark {
+                pid: decoder.i32()?,
+                uri: decoder.string()?,
+            },
+            1 => Command::StartBenchmark,
+            2 => Command::StopBenchmark,
+            3 => Command::Ack,
+            4 => Command::PingPerf,
+            5 => Command::SetIntegration {
+                name: decoder.string()?,
+                version: decoder.string()?,
+            },
+            6 => Command::Err,
+            7 => {
+                let pid = decoder.i32()?;
+                let marker = match decoder.u32()? {
+                    0 => MarkerType::SampleStart(decoder.u64()?),
+                    1 => MarkerType::SampleEnd(decoder.u64()?),
+                    2 => MarkerType::BenchmarkStart(decoder.u64()?),
+                    3 => MarkerType::BenchmarkEnd(decoder.u64()?),
+                    variant => bail!("Unknown marker type: {variant}"),
+                };
+                Command::AddMarker { pid, marker }
+            }
+            8 => Command::SetVersion(decoder.u64()?),
+            9 => Command::SetEnvironment {
+                pid: decoder.i32()?,
+                section: decoder.string()?,
+                key: decoder.string()?,
+                value: decoder.string()?,
+            },
+            10 => Command::WriteEnvironment {
+                pid: decoder.i32()?,
+            },
+            variant => bail!("Unknown command: {variant}"),
+        };
+
+        if !decoder.payload.is_empty() {
+            bail!("Trailing bytes after command {command:?}");
+        }
+        Ok(command)
+    }
+}
+
+/// Reads a single length-prefixed message. Returns `None` if the writer closed the stream.
+pub fn read_message(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error.into()),
+    }
+
+    let len = u32::from_le_bytes(header);
+    if len > MAX_MESSAGE_SIZE {
+        bail!("Message too large: {len} bytes");
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Writes a single length-prefixed message.
+pub fn write_message(writer: &mut impl Write, payload: &[u8]) -> Result<()> {
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, value: i32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    put_u64(buf, value.len() as u64);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+struct Decoder<'a> {
+    payload: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let Some((bytes, rest)) = self.payload.split_first_chunk::<N>() else {
+            bail!("Unexpected end of message");
+        };
+        self.payload = rest;
+        Ok(*bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = usize::try_from(self.u64()?)?;
+        if len > self.payload.len() {
+            bail!("Unexpected end of message");
+        }
+        let (bytes, rest) = self.payload.split_at(len);
+        self.payload = rest;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_matches_bincode_layout() {
+        let command = Command::ExecutedBenchmark {
+            pid: 42,
+            uri: "foo".into(),
+        };
+        assert_eq!(
+            command.encode(),
+            [
+                0, 0, 0, 0, // variant
+                42, 0, 0, 0, // pid
+                3, 0, 0, 0, 0, 0, 0, 0, // string length
+                b'f', b'o', b'o',
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_roundtrip() {
+        let commands = [
+            Command::ExecutedBenchmark {
+                pid: 1,
+                uri: "pkg/foo_test.go::BenchmarkFoo".into(),
+            },
+            Command::StartBenchmark,
+            Command::StopBenchmark,
+            Command::Ack,
+            Command::PingPerf,
+            Command::SetIntegration {
+                name: "codspeed-go".into(),
+                version: "1.0.0".into(),
+            },
+            Command::Err,
+            Command::AddMarker {
+                pid: 7,
+                marker: MarkerType::BenchmarkEnd(u64::MAX),
+            },
+            Command::SetVersion(PROTOCOL_VERSION),
+            Command::SetEnvironment {
+                pid: 7,
+                section: "go".into(),
+                key: "version".into(),
+                value: "go1.25.0".into(),
+            },
+            Command::WriteEnvironment { pid: 7 },
+        ];
+        for command in commands {
+            assert_eq!(Command::decode(&command.encode()).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(Command::decode(&[]).is_err());
+        assert!(Command::decode(&99u32.to_le_bytes()).is_err());
+
+        // Truncated string
+        let mut payload = Command::SetIntegration {
+            name: "name".into(),
+            version: "version".into(),
+        }
+        .encode();
+        payload.pop();
+        assert!(Command::decode(&payload).is_err());
+
+        // Trailing bytes
+        let mut payload = Command::StartBenchmark.encode();
+        payload.push(0);
+        assert!(Command::decode(&payload).is_err());
+    }
+
+    #[test]
+    fn test_read_write_message() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &Command::Ack.encode()).unwrap();
+        write_message(&mut buf, &Command::StopBenchmark.encode()).unwrap();
+
+        let mut reader = buf.as_slice();
+        let first = read_message(&mut reader).unwrap().unwrap();
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(Command::decode(&first).unwrap(), Command::Ack);
+        assert_eq!(Command::decode(&second).unwrap(), Command::StopBenchmark);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
@@ -0,0 +1,287 @@
+use super::protocol::{self, Command, PROTOCOL_VERSION};
+use super::{Environment, HooksEvents};
+use crate::prelude::*;
+use std::{
+    collections::BTreeMap,
+    ffi::CString,
+    fs::{File, OpenOptions},
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+};
+
+/// Receives the commands sent through the instrument hooks, when the benchmarks aren't executed
+/// by the CodSpeed runner.
+pub struct HooksServer {
+    ctl_fifo: PathBuf,
+    ack_fifo: PathBuf,
+    listener: Option<JoinHandle<Result<HooksEvents>>>,
+}
+
+impl HooksServer {
+    /// Starts listening on FIFOs specific to this process, so that concurrent runs don't clash.
+    /// The overlay connects the pure-Go instrument hooks to them, see [`HooksServer::ctl_fifo`].
+    pub fn start() -> Result<Self> {
+        let temp_dir = std::env::temp_dir();
+        let pid = std::process::id();
+        Self::start_at(
+            &temp_dir.join(format!("codspeed-go-runner.{pid}.ctl.fifo")),
+            &temp_dir.join(format!("codspeed-go-runner.{pid}.ack.fifo")),
+        )
+    }
+
+    pub fn start_at(ctl_fifo: &Path, ack_fifo: &Path) -> Result<Self> {
+        if ctl_fifo.exists() || ack_fifo.exists() {
+            bail!(
+                "{ctl_fifo:?} or {ack_fifo:?} already exists. Is another runner listening? If \
+                 not, remove the files and try again."
+            );
+        }
+
+        // Create the server before the FIFOs, so that they are removed on error.
+        let mut server = Self {
+            ctl_fifo: ctl_fifo.to_path_buf(),
+            ack_fifo: ack_fifo.to_path_buf(),
+            listener: None,
+        };
+        create_fifo(ctl_fifo)?;
+        create_fifo(ack_fifo)?;
+
+        // Opening the FIFOs in read-write mode doesn't block until a benchmark process connects,
+        // and prevents EOFs when a benchmark process exits.
+        let ctl = OpenOptions::new().read(true).write(true).open(ctl_fifo)?;
+        let ack = OpenOptions::new().read(true).write(true).open(ack_fifo)?;
+        server.listener = Some(std::thread::spawn(move || listen(ctl, ack)));
+
+        debug!("Listening for instrument hooks on {ctl_fifo:?}");
+        Ok(server)
+    }
+
+    pub fn ctl_fifo(&self) -> &Path {
+        &self.ctl_fifo
+    }
+
+    pub fn ack_fifo(&self) -> &Path {
+        &self.ack_fifo
+    }
+
+    /// Stops the server and returns all the events that have been received.
+    pub fn stop(mut self) -> Result<HooksEvents> {
+        let Some(listener) = self.listener.take() else {
+            bail!("Instrument hooks server isn't running");
+        };
+
+        // An empty message is never sent by the instrument hooks, so we use it to stop the
+        // listener once all the benchmark processes exited. If the listener already exited
+        // because of an error, there's no reader anymore and opening the FIFO fails.
+        if let Ok(mut ctl) = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.ctl_fifo)
+        {
+            let _ = protocol::write_message(&mut ctl, &[]);
+        }
+
+        match listener.join() {
+            Ok(events) => events,
+            Err(_) => bail!("Instrument hooks listener panicked"),
+        }
+    }
+}
+
+impl Drop for HooksServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.ctl_fifo);
+        let _ = std::fs::remove_file(&self.ack_fifo);
+    }
+}
+
+fn create_fifo(path: &Path) -> Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `c_path` is a valid, NUL-terminated C string.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(error).with_context(|| format!("Failed to create FIFO {path:?}"));
+    }
+    Ok(())
+}
+
+fn listen(mut ctl: File, mut ack: File) -> Result<HooksEvents> {
+    let mut events = HooksEvents::default();
+    // Environment values of every process, until the process writes them
+    let mut pending_environments = BTreeMap::new();
+
+    while let Some(payload) = protocol::read_message(&mut ctl)? {
+        if payload.is_empty() {
+            break;
+        }
+
+        let response = match Command::decode(&payload) {
+            Ok(command) => {
+                trace!("Received instrument hooks command: {command:?}");
+                handle_command(&mut events, &mut pending_environments, command)
+            }
+            Err(error) => {
+                warn!("Failed to decode instrument hooks command: {error:#}");
+                Command::Err
+            }
+        };
+        protocol::write_message(&mut ack, &response.encode())?;
+    }
+
+    Ok(events)
+}
+
+fn handle_command(
+    events: &mut HooksEvents,
+    pending_environments: &mut BTreeMap<i32, Environment>,
+    command: Command,
+) -> Command {
+    match command {
+        Command::SetVersion(version) => {
+            if version != PROTOCOL_VERSION {
+                warn!(
+                    "Instrument hooks use protocol version {version}, but the runner supports \
+                     version {PROTOCOL_VERSION}"
+                );
+            }
+            events.protocol_version = Some(version);
+        }
+        Command::SetIntegration { name, version } => {
+            events.integration = Some(super::Integration { name, version });
+        }
+        Command::ExecutedBenchmark { pid, uri } => {
+            events
+                .executed_benchmarks
+                .push(super::ExecutedBenchmark { pid, uri });
+        }
+        Command::AddMarker { pid, marker } => {
+            events.markers.push(super::Marker { pid, marker });
+        }
+        Command::SetEnvironment {
+            pid,
+            section,
+            key,
+            value,
+        } => {
+            pending_environments
+                .entry(pid)
+                .or_default()
+                .entry(section)
+                .or_default()
+                .insert(key, value);
+        }
+        Command::WriteEnvironment { pid } => {
+            let environment = pending_environments.remove(&pid).unwrap_or_default();
+            events.environments.insert(pid, environment);
+        }
+        Command::StartBenchmark | Command::StopBenchmark | Command::PingPerf => {}
+        Command::Ack | Command::Err => {
+            warn!("Unexpected instrument hooks command: {command:?}");
+            return Command::Err;
+        }
+    }
+
+    Command::Ack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::protocol::MarkerType;
+    use tempfile::TempDir;
+
+    fn send(ctl: &mut File, ack: &mut File, command: Command) -> Command {
+        protocol::write_message(ctl, &command.encode()).unwrap();
+        let response = protocol::read_message(ack).unwrap().unwrap();
+        Command::decode(&response).unwrap()
+    }
+
+    #[test]
+    fn test_server_collects_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let ctl_fifo = temp_dir.path().join("runner.ctl.fifo");
+        let ack_fifo = temp_dir.path().join("runner.ack.fifo");
+        let server = HooksServer::start_at(&ctl_fifo, &ack_fifo).unwrap();
+
+        let mut ctl = OpenOptions::new().write(true).open(&ctl_fifo).unwrap();
+        let mut ack = OpenOptions::new().read(true).open(&ack_fifo).unwrap();
+
+        let commands = [
+            Command::SetVersion(PROTOCOL_VERSION),
+            Command::SetIntegration {
+                name: "codspeed-go".into(),
+                version: "1.0.0".into(),
+            },
+            Command::StartBenchmark,
+            Command::StopBenchmark,
+            Command::AddMarker {
+                pid: 42,
+                marker: MarkerType::BenchmarkStart(10),
+            },
+            Command::AddMarker {
+                pid: 42,
+                marker: MarkerType::BenchmarkEnd(20),
+            },
+            Command::ExecutedBenchmark {
+                pid: 42,
+                uri: "foo_test.go::BenchmarkFoo".into(),
+            },
+            Command::SetEnvironment {
+                pid: 42,
+                section: "go".into(),
+                key: "version".into(),
+                value: "go1.25.0".into(),
+            },
+            // Not written, so it's not part of the events
+            Command::SetEnvironment {
+                pid: 43,
+                section: "go".into(),
+                key: "version".into(),
+                value: "go1.24.0".into(),
+            },
+            Command::WriteEnvironment { pid: 42 },
+        ];
+        for command in commands {
+            assert_eq!(send(&mut ctl, &mut ack, command), Command::Ack);
+        }
+        assert_eq!(send(&mut ctl, &mut ack, Command::Ack), Command::Err);
+
+        let events = server.stop().unwrap();
+        assert_eq!(events.protocol_version, Some(PROTOCOL_VERSION));
+        assert_eq!(events.integration.as_ref().unwrap().name, "codspeed-go");
+        assert_eq!(events.executed_benchmarks.len(), 1);
+        assert_eq!(events.markers.len(), 2);
+        assert_eq!(events.environments.len(), 1);
+        assert_eq!(events.environments[&42]["go"]["version"], "go1.25.0");
+        events.validate().unwrap();
+
+        assert!(!ctl_fifo.exists());
+        assert!(!ack_fifo.exists());
+    }
+
+    #[test]
+    fn test_server_fifos_are_specific_to_the_process() {
+        let server = HooksServer::start().unwrap();
+        let pid = std::process::id().to_string();
+        assert!(server.ctl_fifo().to_string_lossy().contains(&pid));
+        assert!(server.ack_fifo().to_string_lossy().contains(&pid));
+        assert_ne!(server.ctl_fifo(), Path::new(protocol::RUNNER_CTL_FIFO));
+        assert!(server.ctl_fifo().exists());
+
+        let ctl_fifo = server.ctl_fifo().to_path_buf();
+        server.stop().unwrap();
+        assert!(!ctl_fifo.exists());
+    }
+
+    #[test]
+    fn test_server_refuses_existing_fifo() {
+        let temp_dir = TempDir::new().unwrap();
+        let ctl_fifo = temp_dir.path().join("runner.ctl.fifo");
+        let ack_fifo = temp_dir.path().join("runner.ack.fifo");
+        std::fs::write(&ctl_fifo, "").unwrap();
+
+        assert!(HooksServer::start_at(&ctl_fifo, &ack_fifo).is_err());
+        assert!(ctl_fifo.exists(), "existing files must not be removed");
+    }
+}
@@ -1,10 +1,24 @@
 use crate::{
+    config::{Config, ResultsConfig},
+    hooks::{HooksEvents, HooksServer},
     prelude::*,
-    results::{raw_result::RawResult, walltime_results::WalltimeBenchmark},
+    results::{
+        archive,
+        raw_result::{ParsedBenchmark, RawResult},
+        run_metadata::RunMetadata,
+        stability::{BenchmarkStability, StabilityReport},
+        summary::RunSummary,
+        walltime_results::{UnmeasuredBenchmark, WalltimeBenchmark},
+    },
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
 };
-use std::{collections::HashMap, path::Path};
 
 pub mod cli;
+pub mod config;
+pub mod hooks;
 pub mod prelude;
 pub mod results;
 pub mod runner;
@@ -21,29 +35,95 @@ pub fn run_benchmarks<P: AsRef<Path>>(
     project_dir: P,
     cli: &crate::cli::Cli,
 ) -> anyhow::Result<()> {
-    if let Err(error) = runner::run(&profile_dir, &project_dir, cli) {
-        bail!("Failed to run benchmarks: {error}");
+    let hooks_server = if cli.local_hooks {
+        if std::env::var_os("CODSPEED_ENV").is_some() {
+            warn!(
+                "Ignoring --local-hooks, the instrument hooks are handled by the CodSpeed runner"
+            );
+            None
+        } else {
+            Some(HooksServer::start()?)
+        }
+    } else {
+        None
+    };
+
+    let config = Config::from_cli(cli)?;
+    let run_result = runner::run(
+        &profile_dir,
+        &project_dir,
+        cli,
+        &config,
+        hooks_server.as_ref(),
+    );
+
+    let hooks_events = hooks_server.map(HooksServer::stop).transpose()?;
+    if let Some(hooks_events) = &hooks_events {
+        info!(
+            "Received {} executed benchmarks and {} markers from the instrument hooks",
+            hooks_events.executed_benchmarks.len(),
+            hooks_events.markers.len()
+        );
     }
 
+    // The results are collected even when `go test` failed, so that the failed benchmarks are
+    // reported along with the ones that ran. Likewise, invalid hooks events (e.g. a benchmark that
+    // panicked before being stopped) are only reported once the results are collected.
     let profile_dir = profile_dir.as_ref().to_path_buf();
-    collect_walltime_results(&profile_dir).unwrap();
+    let collect_result =
+        collect_walltime_results(&profile_dir, hooks_events.as_ref(), &config.results);
+
+    if let Err(error) = run_result {
+        // The failure of `go test` is the root cause of a collection error
+        let error = match collect_result {
+            Ok(()) => error,
+            Err(collect_error) => {
+                error.context(format!("Failed to collect the results: {collect_error:#}"))
+            }
+        };
+        return Err(error.context("Failed to run benchmarks"));
+    }
+    collect_result?;
 
+    if let Some(hooks_events) = &hooks_events {
+        hooks_events.validate()?;
+    }
     Ok(())
 }
 
 // TODO: This should be merged with codspeed-rust/codspeed/walltime_results.rs
-pub fn collect_walltime_results(profile_dir: &Path) -> anyhow::Result<()> {
-    let mut benchmarks_by_pid: HashMap<u32, Vec<WalltimeBenchmark>> = HashMap::new();
+pub fn collect_walltime_results(
+    profile_dir: &Path,
+    hooks_events: Option<&HooksEvents>,
+    config: &ResultsConfig,
+) -> anyhow::Result<()> {
+    let mut benchmarks_by_pid: HashMap<u32, (Vec<WalltimeBenchmark>, Vec<UnmeasuredBenchmark>)> =
+        HashMap::new();
+    let mut summary = RunSummary::default();
+    let mut stability = Vec::new();
 
     let raw_results_dir = profile_dir.join("raw_results");
-    for (pid, walltime_result) in RawResult::parse_folder(&raw_results_dir)?.into_iter() {
-        benchmarks_by_pid
-            .entry(pid)
-            .or_default()
-            .push(walltime_result);
+    let parsed = RawResult::parse_folder(&raw_results_dir, config)?;
+    for invalid in &parsed.invalid {
+        summary.add_invalid(invalid);
+    }
+    for (pid, benchmark) in parsed.benchmarks {
+        summary.add(&benchmark);
+
+        let (measured, unmeasured) = benchmarks_by_pid.entry(pid).or_default();
+        match benchmark {
+            ParsedBenchmark::Measured(benchmark) => {
+                stability.push(BenchmarkStability::new(pid, &benchmark));
+                measured.push(*benchmark);
+            }
+            ParsedBenchmark::Unmeasured(benchmark) => unmeasured.push(benchmark),
+        }
     }
+    summary.log();
+    let stability = StabilityReport::new(stability);
+    stability.log();
 
-    for (pid, walltime_benchmarks) in benchmarks_by_pid {
+    for (pid, (walltime_benchmarks, unmeasured_benchmarks)) in benchmarks_by_pid {
         let creator = results::walltime_results::Creator {
             name: "codspeed-go".into(),
             version: env!("CARGO_PKG_VERSION").into(),
@@ -53,11 +133,167 @@ pub fn collect_walltime_results(profile_dir: &Path) -> anyhow::Result<()> {
         std::fs::create_dir_all(&results_dir)?;
 
         let results_file = results_dir.join(format!("{pid}.json"));
-        let walltime_results =
+        let mut walltime_results =
             results::walltime_results::WalltimeResults::new(walltime_benchmarks, creator)?;
+        walltime_results.unmeasured_benchmarks = unmeasured_benchmarks;
+        walltime_results.instrument_hooks = hooks_events.map(|events| events.for_pid(pid));
         std::fs::write(&results_file, serde_json::to_string(&walltime_results)?)?;
         info!("Results written to {results_file:?}");
     }
 
+    let metadata = RunMetadata::collect(profile_dir);
+    let environments: BTreeMap<u32, PathBuf> = metadata
+        .pids
+        .iter()
+        .map(|&pid| (pid, hooks::environment_path(profile_dir, pid)))
+        .collect();
+    if config.keep_raw_results {
+        archive::archive_raw_results(
+            profile_dir,
+            &archive::new_run_id(),
+            &parsed.files,
+            &environments,
+            config.compress_raw_results,
+        )?;
+    }
+    metadata.write(profile_dir)?;
+    stability.write(profile_dir)?;
+
+    // Like the raw results, the environments are only collected once, so that the metadata of
+    // the next run doesn't include the processes of this one
+    for path in environments.values() {
+        if let Err(error) = std::fs::remove_file(path) {
+            warn!("Failed to remove the environment {path:?}: {error}");
+        }
+    }
+
+    if !parsed.invalid.is_empty() && !config.allow_invalid_raw_results {
+        bail!(
+            "{} raw result(s) couldn't be parsed, use --allow-invalid-raw-results to ignore them",
+            parsed.invalid.len()
+        );
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::walltime_results::WalltimeResults;
+    use tempfile::TempDir;
+
+    fn profile_dir_with_invalid_raw_result() -> TempDir {
+        let profile_dir = TempDir::new().unwrap();
+        let raw_results_dir = profile_dir.path().join("raw_results");
+        std::fs::create_dir(&raw_results_dir).unwrap();
+        std::fs::write(raw_results_dir.join("invalid.json"), "{").unwrap();
+        profile_dir
+    }
+
+    #[test]
+    fn test_collect_fails_on_invalid_raw_results() {
+        let profile_dir = profile_dir_with_invalid_raw_result();
+        let error = collect_walltime_results(profile_dir.path(), None, &ResultsConfig::default())
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("1 raw result(s) couldn't be parsed")
+        );
+
+        let quarantined = profile_dir
+            .path()
+            .join("raw_results")
+            .join(RawResult::QUARANTINE_DIR)
+            .join("invalid.json");
+        assert!(quarantined.exists());
+    }
+
+    #[test]
+    fn test_collect_allows_invalid_raw_results() {
+        let profile_dir = profile_dir_with_invalid_raw_result();
+        let config = ResultsConfig {
+            allow_invalid_raw_results: true,
+            ..Default::default()
+        };
+        collect_walltime_results(profile_dir.path(), None, &config).unwrap();
+    }
+
+    #[test]
+    fn test_collect_archives_raw_results() {
+        let profile_dir = TempDir::new().unwrap();
+        let raw_results_dir = profile_dir.path().join("raw_results");
+        std::fs::create_dir(&raw_results_dir).unwrap();
+        let write_raw_result = |name: &str, pid: u32| {
+            std::fs::write(
+                raw_results_dir.join(format!("{name}.json")),
+                format!(
+                    r#"{{
+    "name": "{name}",
+    "uri": "foo_test.go::{name}",
+    "pid": {pid},
+    "codspeed_time_per_round_ns": [1000],
+    "codspeed_iters_per_round": [1]
+}}"#
+                ),
+            )
+            .unwrap();
+        };
+        let environment_dir = profile_dir.path().join("environment");
+        std::fs::create_dir(&environment_dir).unwrap();
+        let write_environment = |pid: u32| {
+            std::fs::write(
+                environment_dir.join(format!("{pid}.json")),
+                r#"{"go": {"version": "go1.24.5"}}"#,
+            )
+            .unwrap();
+        };
+        let config = ResultsConfig {
+            keep_raw_results: true,
+            compress_raw_results: true,
+            ..Default::default()
+        };
+
+        write_raw_result("BenchmarkFoo", 1);
+        write_environment(1);
+        collect_walltime_results(profile_dir.path(), None, &config).unwrap();
+        assert!(!raw_results_dir.join("BenchmarkFoo.json").exists());
+        assert!(!environment_dir.join("1.json").exists());
+
+        // The second run only collects its own raw results and environments
+        write_raw_result("BenchmarkBar", 2);
+        write_environment(2);
+        collect_walltime_results(profile_dir.path(), None, &config).unwrap();
+
+        let metadata = std::fs::read_to_string(RunMetadata::path(profile_dir.path())).unwrap();
+        let metadata: RunMetadata = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(metadata.pids, vec![2]);
+
+        let stability = std::fs::read_to_string(StabilityReport::path(profile_dir.path())).unwrap();
+        let stability: StabilityReport = serde_json::from_str(&stability).unwrap();
+        assert_eq!(stability.stable, 1);
+        assert_eq!(stability.benchmarks[0].pid, 2);
+
+        let results_file = profile_dir.path().join("results").join("2.json");
+        let results: WalltimeResults =
+            serde_json::from_str(&std::fs::read_to_string(results_file).unwrap()).unwrap();
+        assert_eq!(results.benchmarks.len(), 1);
+        assert_eq!(results.benchmarks[0].metadata.name, "BenchmarkBar");
+
+        let manifest = archive::Manifest::load(profile_dir.path()).unwrap();
+        let runs: Vec<_> = manifest.runs.values().collect();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0][0].raw_results, vec!["BenchmarkFoo.json"]);
+        assert_eq!(runs[1][0].raw_results, vec!["BenchmarkBar.json"]);
+        assert_eq!(
+            runs[1][0].environment.as_deref(),
+            Some(archive::ENVIRONMENT_FILE)
+        );
+        assert!(runs.iter().all(|entries| {
+            entries
+                .iter()
+                .all(|entry| profile_dir.path().join(&entry.archive).exists())
+        }));
+    }
+}
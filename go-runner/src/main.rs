@@ -1,4 +1,5 @@
-use codspeed_go_runner::cli::Cli;
+use codspeed_go_runner::cli::Command;
+use codspeed_go_runner::results::compare;
 use std::path::Path;
 
 fn main() -> anyhow::Result<()> {
@@ -9,9 +10,19 @@ fn main() -> anyhow::Result<()> {
         .format_timestamp(None)
         .init();
 
-    let cli = Cli::parse();
-    let profile_dir = std::env::var("CODSPEED_PROFILE_FOLDER").unwrap_or("/tmp".into());
-    codspeed_go_runner::run_benchmarks(Path::new(&profile_dir), Path::new("."), &cli)?;
+    match Command::parse() {
+        Command::Test(cli) => {
+            let profile_dir = std::env::var("CODSPEED_PROFILE_FOLDER").unwrap_or("/tmp".into());
+            codspeed_go_runner::run_benchmarks(Path::new(&profile_dir), Path::new("."), &cli)?;
+        }
+        Command::Compare(cli) => {
+            let comparison = compare::compare(&cli.baseline, &cli.candidate, &cli.options)?;
+            print!("{}", comparison.table());
+            if comparison.has_regressions() {
+                std::process::exit(compare::REGRESSION_EXIT_CODE);
+            }
+        }
+    }
 
     Ok(())
 }
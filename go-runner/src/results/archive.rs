@@ -0,0 +1,295 @@
+//! Archive of the raw results, written when they're kept (see
+//! [`ResultsConfig::keep_raw_results`](crate::config::ResultsConfig::keep_raw_results)).
+//!
+//! The raw results of each run are moved to `<profile dir>/raw_results_archive/<run id>/`, either
+//! to `<pid>/` or compressed to `<pid>.tar.gz` for every benchmark process, so that the next run
+//! doesn't collect them again. The environment of the process (see
+//! [`load_environment`](crate::hooks::load_environment)) is archived with them, as
+//! `environment.json`. The `manifest.json` links every archive to the results generated from it,
+//! by run.
+
+use crate::prelude::*;
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const ARCHIVE_DIR: &str = "raw_results_archive";
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Name of the environment of the process in its archive.
+pub const ENVIRONMENT_FILE: &str = "environment.json";
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Archived raw results, by run id.
+    pub runs: BTreeMap<String, Vec<ManifestEntry>>,
+}
+
+/// Raw results of a benchmark process. The paths are relative to the profile dir.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub pid: u32,
+    /// e.g. `results/<pid>.json`
+    pub results: PathBuf,
+    /// e.g. `raw_results_archive/<run id>/<pid>.tar.gz`
+    pub archive: PathBuf,
+    /// File names of the raw results in the archive.
+    pub raw_results: Vec<String>,
+    /// File name of the environment in the archive, when the process wrote one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+impl Manifest {
+    pub fn path(profile_dir: &Path) -> PathBuf {
+        profile_dir.join(ARCHIVE_DIR).join(MANIFEST_FILE)
+    }
+
+    pub fn load(profile_dir: &Path) -> Result<Self> {
+        let path = Self::path(profile_dir);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read the manifest {path:?}"))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse the manifest {path:?}"))
+    }
+}
+
+/// Returns a new run id, which sorts in the order of the runs.
+pub fn new_run_id() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}-{}", since_epoch.as_nanos(), std::process::id())
+}
+
+/// Archives the raw results of every benchmark process of the run along with its environment file,
+/// and adds them to the manifest. The raw results are only removed once the manifest is written,
+/// so that they're never lost. The environment files are removed by the caller.
+pub fn archive_raw_results(
+    profile_dir: &Path,
+    run_id: &str,
+    files_by_pid: &BTreeMap<u32, Vec<PathBuf>>,
+    environments: &BTreeMap<u32, PathBuf>,
+    compress: bool,
+) -> Result<Vec<ManifestEntry>> {
+    if files_by_pid.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let run_dir = PathBuf::from(ARCHIVE_DIR).join(run_id);
+    if profile_dir.join(&run_dir).exists() {
+        bail!("The raw results of run {run_id} are already archived");
+    }
+    std::fs::create_dir_all(profile_dir.join(&run_dir))
+        .with_context(|| format!("Failed to create the archive folder {run_dir:?}"))?;
+
+    let mut entries = Vec::new();
+    for (&pid, files) in files_by_pid {
+        let environment = environments.get(&pid).filter(|path| path.exists());
+        let mut named_files: Vec<_> = files.iter().map(|file| (file, file_name(file))).collect();
+        named_files.extend(environment.map(|path| (path, ENVIRONMENT_FILE.to_string())));
+
+        let archive = if compress {
+            let archive = run_dir.join(format!("{pid}.tar.gz"));
+            write_tar_gz(&profile_dir.join(&archive), &named_files)?;
+            archive
+        } else {
+            let archive = run_dir.join(pid.to_string());
+            link_to_dir(&profile_dir.join(&archive), &named_files)?;
+            archive
+        };
+
+        entries.push(ManifestEntry {
+            pid,
+            results: PathBuf::from("results").join(format!("{pid}.json")),
+            archive,
+            raw_results: files.iter().map(|file| file_name(file)).collect(),
+            environment: environment.map(|_| ENVIRONMENT_FILE.to_string()),
+        });
+    }
+
+    let manifest_path = Manifest::path(profile_dir);
+    let mut manifest = if manifest_path.exists() {
+        Manifest::load(profile_dir)?
+    } else {
+        Manifest::default()
+    };
+    manifest.runs.insert(run_id.to_string(), entries.clone());
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write the manifest {manifest_path:?}"))?;
+
+    for file in files_by_pid.values().flatten() {
+        if let Err(error) = std::fs::remove_file(file) {
+            warn!("Failed to remove archived raw result {file:?}: {error}");
+        }
+    }
+    info!("Raw results archived to {run_dir:?}");
+
+    Ok(entries)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Hard links the files into `dir` with the given names, or copies them when that's not possible
+/// (e.g. across file systems). The originals are removed by the caller.
+fn link_to_dir(dir: &Path, files: &[(&PathBuf, String)]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (file, name) in files {
+        let archived = dir.join(name);
+        std::fs::hard_link(file, &archived)
+            .or_else(|_| std::fs::copy(file, &archived).map(|_| ()))
+            .with_context(|| format!("Failed to archive {file:?}"))?;
+    }
+    Ok(())
+}
+
+fn write_tar_gz(path: &Path, files: &[(&PathBuf, String)]) -> Result<()> {
+    let archive = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create the archive {path:?}"))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+    for (file, name) in files {
+        builder
+            .append_path_with_name(file, name)
+            .with_context(|| format!("Failed to archive {file:?}"))?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use tempfile::TempDir;
+
+    fn raw_results(profile_dir: &Path) -> BTreeMap<u32, Vec<PathBuf>> {
+        let raw_results_dir = profile_dir.join("raw_results");
+        std::fs::create_dir_all(&raw_results_dir).unwrap();
+        let mut files_by_pid = BTreeMap::new();
+        for (pid, name) in [(1, "a.json"), (1, "b.json"), (2, "c.json")] {
+            let path = raw_results_dir.join(name);
+            std::fs::write(&path, name).unwrap();
+            files_by_pid.entry(pid).or_insert_with(Vec::new).push(path);
+        }
+        files_by_pid
+    }
+
+    #[test]
+    fn test_archive_raw_results() {
+        let profile_dir = TempDir::new().unwrap();
+        let files_by_pid = raw_results(profile_dir.path());
+
+        let entries = archive_raw_results(
+            profile_dir.path(),
+            "1",
+            &files_by_pid,
+            &BTreeMap::new(),
+            false,
+        )
+        .unwrap();
+        let manifest = Manifest::load(profile_dir.path()).unwrap();
+        assert_eq!(manifest.runs["1"], entries);
+        assert_eq!(entries.len(), 2);
+
+        let entry = &entries[0];
+        assert_eq!(entry.pid, 1);
+        assert_eq!(entry.results, PathBuf::from("results/1.json"));
+        assert_eq!(entry.archive, PathBuf::from("raw_results_archive/1/1"));
+        assert_eq!(entry.raw_results, vec!["a.json", "b.json"]);
+        let archived = profile_dir.path().join(&entry.archive).join("b.json");
+        assert_eq!(std::fs::read_to_string(archived).unwrap(), "b.json");
+
+        // The raw results are moved to the archive
+        assert!(files_by_pid.values().flatten().all(|file| !file.exists()));
+
+        // The archives of the next runs are added to the manifest
+        let files_by_pid = raw_results(profile_dir.path());
+        archive_raw_results(
+            profile_dir.path(),
+            "2",
+            &files_by_pid,
+            &BTreeMap::new(),
+            false,
+        )
+        .unwrap();
+        let manifest = Manifest::load(profile_dir.path()).unwrap();
+        assert_eq!(manifest.runs.keys().collect::<Vec<_>>(), ["1", "2"]);
+        assert_eq!(manifest.runs["1"], entries);
+
+        // A run can't be archived twice
+        let files_by_pid = raw_results(profile_dir.path());
+        assert!(
+            archive_raw_results(
+                profile_dir.path(),
+                "2",
+                &files_by_pid,
+                &BTreeMap::new(),
+                false
+            )
+            .is_err()
+        );
+        assert!(files_by_pid.values().flatten().all(|file| file.exists()));
+    }
+
+    #[test]
+    fn test_archive_without_raw_results() {
+        let profile_dir = TempDir::new().unwrap();
+        let entries = archive_raw_results(
+            profile_dir.path(),
+            "1",
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+            false,
+        )
+        .unwrap();
+        assert!(entries.is_empty());
+        assert!(!Manifest::path(profile_dir.path()).exists());
+    }
+
+    #[test]
+    fn test_archive_raw_results_compressed() {
+        let profile_dir = TempDir::new().unwrap();
+        let files_by_pid = raw_results(profile_dir.path());
+
+        let environment = profile_dir.path().join("2.json");
+        std::fs::write(&environment, "{}").unwrap();
+        let environments = BTreeMap::from([(2, environment.clone())]);
+
+        let entries =
+            archive_raw_results(profile_dir.path(), "1", &files_by_pid, &environments, true)
+                .unwrap();
+        assert_eq!(entries[0].environment, None);
+        let entry = &entries[1];
+        assert_eq!(entry.environment.as_deref(), Some(ENVIRONMENT_FILE));
+        assert_eq!(
+            entry.archive,
+            PathBuf::from("raw_results_archive/1/2.tar.gz")
+        );
+
+        // Only the compressed archive is kept
+        assert!(files_by_pid.values().flatten().all(|file| !file.exists()));
+
+        let archive = std::fs::File::open(profile_dir.path().join(&entry.archive)).unwrap();
+        let mut archive = tar::Archive::new(GzDecoder::new(archive));
+        let names: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                entry
+                    .unwrap()
+                    .path()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(names, ["c.json", ENVIRONMENT_FILE]);
+        // The environment files are removed by the caller
+        assert!(environment.exists());
+    }
+}
@@ -0,0 +1,629 @@
+//! Comparison of the results of two runs (`go-runner compare <baseline> <candidate>`), to check
+//! a change locally.
+//!
+//! The benchmarks are matched by URI. A benchmark is regressed (or improved) when its mean
+//! changed by more than [`CompareOptions::threshold`] and the change is significant according to
+//! Welch's t-test, computed from the mean, standard deviation and number of rounds of both runs.
+//! The verdict and the test are both about the mean, since the results don't have the rounds
+//! that a test on the median would need.
+
+use crate::prelude::*;
+use crate::results::walltime_results::{BenchmarkStats, BenchmarkStatus, WalltimeResults};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Exit code of `go-runner compare` when some benchmarks regressed or failed, distinct from the
+/// exit codes of the invalid command lines.
+pub const REGRESSION_EXIT_CODE: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompareOptions {
+    /// Relative change of the mean below which a benchmark is unchanged, e.g. `0.05` for 5%.
+    pub threshold: f64,
+    /// Maximum p-value of a significant change.
+    pub significance_level: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.05,
+            significance_level: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    Regressed,
+    /// Failed in the candidate run.
+    Failed,
+    Improved,
+    /// Not enough rounds (fewer than 2 in one of the runs) to tell whether the change is
+    /// significant.
+    Inconclusive,
+    Unchanged,
+    /// Only in the candidate results.
+    Added,
+    /// Only in the baseline results.
+    Removed,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Regressed => "regressed",
+            Verdict::Failed => "failed",
+            Verdict::Improved => "improved",
+            Verdict::Inconclusive => "inconclusive",
+            Verdict::Unchanged => "unchanged",
+            Verdict::Added => "added",
+            Verdict::Removed => "removed",
+        }
+    }
+}
+
+/// Median and mean time per iteration of a benchmark in one of the runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub median_ns: f64,
+    pub mean_ns: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkComparison {
+    pub uri: String,
+    pub baseline: Option<Measurement>,
+    pub candidate: Option<Measurement>,
+    /// Relative changes from the baseline, positive when the candidate is slower.
+    pub median_change: Option<f64>,
+    pub mean_change: Option<f64>,
+    /// Only available when both runs have at least 2 rounds.
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// Sorted by verdict (regressions first), then by change.
+    pub benchmarks: Vec<BenchmarkComparison>,
+}
+
+impl Comparison {
+    pub fn count(&self, verdict: Verdict) -> usize {
+        self.benchmarks
+            .iter()
+            .filter(|benchmark| benchmark.verdict == verdict)
+            .count()
+    }
+
+    /// Whether some benchmarks regressed or failed in the candidate run.
+    pub fn has_regressions(&self) -> bool {
+        self.count(Verdict::Regressed) > 0 || self.count(Verdict::Failed) > 0
+    }
+
+    /// Formats the comparison as a table, followed by a summary line.
+    pub fn table(&self) -> String {
+        let header = [
+            "Benchmark",
+            "Baseline",
+            "Candidate",
+            "Mean",
+            "Median",
+            "p-value",
+            "Result",
+        ]
+        .map(String::from);
+        let rows: Vec<[String; 7]> = self
+            .benchmarks
+            .iter()
+            .map(|benchmark| {
+                [
+                    benchmark.uri.clone(),
+                    format_time(benchmark.baseline.map(|m| m.mean_ns)),
+                    format_time(benchmark.candidate.map(|m| m.mean_ns)),
+                    format_change(benchmark.mean_change),
+                    format_change(benchmark.median_change),
+                    benchmark
+                        .p_value
+                        .map_or("-".into(), |p_value| format!("{p_value:.3}")),
+                    benchmark.verdict.as_str().into(),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.clone().map(|column| column.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let cells: Vec<_> = row
+                .iter()
+                .zip(widths)
+                .enumerate()
+                .map(|(column, (cell, width))| {
+                    // Left-align the names, right-align the numbers
+                    if column == 0 || column == row.len() - 1 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .collect();
+            table.push_str(cells.join("  ").trim_end());
+            table.push('\n');
+        }
+
+        table.push_str(&format!(
+            "\n{} regressed, {} failed, {} improved, {} inconclusive, {} unchanged, {} added, \
+             {} removed\n",
+            self.count(Verdict::Regressed),
+            self.count(Verdict::Failed),
+            self.count(Verdict::Improved),
+            self.count(Verdict::Inconclusive),
+            self.count(Verdict::Unchanged),
+            self.count(Verdict::Added),
+            self.count(Verdict::Removed),
+        ));
+        table
+    }
+}
+
+/// Compares the results of two profile folders (or `results` folders).
+pub fn compare(baseline: &Path, candidate: &Path, options: &CompareOptions) -> Result<Comparison> {
+    let (baseline, _) = load_stats(baseline)?;
+    let (mut candidate, mut candidate_failed) = load_stats(candidate)?;
+
+    let mut benchmarks = Vec::new();
+    for (uri, baseline) in baseline {
+        let comparison = match candidate.remove(&uri) {
+            Some(candidate) => compare_stats(uri, &baseline, &candidate, options),
+            None => {
+                let verdict = if candidate_failed.remove(&uri) {
+                    Verdict::Failed
+                } else {
+                    Verdict::Removed
+                };
+                BenchmarkComparison {
+                    uri,
+                    baseline: Some(measurement(&baseline)),
+                    candidate: None,
+                    median_change: None,
+                    mean_change: None,
+                    p_value: None,
+                    verdict,
+                }
+            }
+        };
+        benchmarks.push(comparison);
+    }
+    // Failed in the candidate run, without measurements in any of the runs
+    benchmarks.extend(
+        candidate_failed
+            .into_iter()
+            .filter(|uri| !candidate.contains_key(uri))
+            .map(|uri| BenchmarkComparison {
+                uri,
+                baseline: None,
+                candidate: None,
+                median_change: None,
+                mean_change: None,
+                p_value: None,
+                verdict: Verdict::Failed,
+            }),
+    );
+    benchmarks.extend(
+        candidate
+            .into_iter()
+            .map(|(uri, candidate)| BenchmarkComparison {
+                uri,
+                baseline: None,
+                candidate: Some(measurement(&candidate)),
+                median_change: None,
+                mean_change: None,
+                p_value: None,
+                verdict: Verdict::Added,
+            }),
+    );
+
+    benchmarks.sort_by(|a, b| {
+        a.verdict.cmp(&b.verdict).then_with(|| {
+            let change = |benchmark: &BenchmarkComparison| benchmark.mean_change.unwrap_or(0.0);
+            // The largest changes first, whether they're regressions or improvements
+            change(b).abs().total_cmp(&change(a).abs())
+        })
+    });
+    Ok(Comparison { benchmarks })
+}
+
+/// Loads the stats of every benchmark of `<dir>/results/*.json` by URI, along with the URIs of
+/// the benchmarks that failed.
+fn load_stats(dir: &Path) -> Result<(BTreeMap<String, BenchmarkStats>, BTreeSet<String>)> {
+    let results_dir = if dir.join("results").is_dir() {
+        dir.join("results")
+    } else {
+        dir.to_path_buf()
+    };
+    let glob_pattern = results_dir.join("*.json");
+    let paths: Vec<PathBuf> = glob::glob(&glob_pattern.to_string_lossy())?
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Failed to list the results of {results_dir:?}"))?;
+    if paths.is_empty() {
+        bail!("No results found in {results_dir:?}");
+    }
+
+    let mut stats = BTreeMap::new();
+    let mut failed = BTreeSet::new();
+    for path in paths {
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Failed to open the results {path:?}"))?;
+        let results: WalltimeResults = serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to parse the results {path:?}"))?;
+        for benchmark in results.benchmarks {
+            let uri = benchmark.metadata.uri.clone();
+            if stats.insert(uri.clone(), benchmark.stats).is_some() {
+                warn!(
+                    "Benchmark {uri} is in several results of {results_dir:?}, keeping the last one"
+                );
+            }
+        }
+        failed.extend(
+            results
+                .unmeasured_benchmarks
+                .into_iter()
+                .filter(|benchmark| benchmark.status == BenchmarkStatus::Failed)
+                .map(|benchmark| benchmark.metadata.uri),
+        );
+    }
+    Ok((stats, failed))
+}
+
+fn measurement(stats: &BenchmarkStats) -> Measurement {
+    Measurement {
+        median_ns: stats.median_ns(),
+        mean_ns: stats.mean_ns(),
+    }
+}
+
+fn compare_stats(
+    uri: String,
+    baseline: &BenchmarkStats,
+    candidate: &BenchmarkStats,
+    options: &CompareOptions,
+) -> BenchmarkComparison {
+    let relative_change =
+        |baseline: f64, candidate: f64| (baseline > 0.0).then(|| (candidate - baseline) / baseline);
+    let median_change = relative_change(baseline.median_ns(), candidate.median_ns());
+    let mean_change = relative_change(baseline.mean_ns(), candidate.mean_ns());
+    let p_value = welch_t_test(baseline, candidate);
+
+    let verdict = match (mean_change, p_value) {
+        (_, None) => Verdict::Inconclusive,
+        (Some(change), Some(p_value)) if p_value < options.significance_level => {
+            if change > options.threshold {
+                Verdict::Regressed
+            } else if change < -options.threshold {
+                Verdict::Improved
+            } else {
+                Verdict::Unchanged
+            }
+        }
+        _ => Verdict::Unchanged,
+    };
+
+    BenchmarkComparison {
+        uri,
+        baseline: Some(measurement(baseline)),
+        candidate: Some(measurement(candidate)),
+        median_change,
+        mean_change,
+        p_value,
+        verdict,
+    }
+}
+
+/// Two-sided p-value of Welch's t-test between the rounds of both runs, from their mean,
+/// standard deviation and number of rounds.
+fn welch_t_test(a: &BenchmarkStats, b: &BenchmarkStats) -> Option<f64> {
+    if a.rounds() < 2 || b.rounds() < 2 {
+        return None;
+    }
+
+    let variance_of_mean =
+        |stats: &BenchmarkStats| stats.stdev_ns().powi(2) / stats.rounds() as f64;
+    let (var_a, var_b) = (variance_of_mean(a), variance_of_mean(b));
+    let difference = b.mean_ns() - a.mean_ns();
+    if var_a + var_b == 0.0 {
+        // Both runs are perfectly stable
+        return Some(if difference == 0.0 { 1.0 } else { 0.0 });
+    }
+
+    let t = difference / (var_a + var_b).sqrt();
+    // Welch–Satterthwaite degrees of freedom
+    let df = (var_a + var_b).powi(2)
+        / (var_a.powi(2) / (a.rounds() - 1) as f64 + var_b.powi(2) / (b.rounds() - 1) as f64);
+    Some(student_t_p_value(t, df))
+}
+
+/// Two-sided p-value of Student's t distribution with `df` degrees of freedom.
+fn student_t_p_value(t: f64, df: f64) -> f64 {
+    incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`, evaluated with its continued fraction
+/// (Numerical Recipes, 6.4).
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    let front = ln_front.exp();
+    // The continued fraction converges quickly below this point, use the symmetry above it
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 300;
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    let clamp = |value: f64| if value.abs() < TINY { TINY } else { value };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut result = d;
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let even = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        result *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = d * c;
+        result *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    result
+}
+
+/// Logarithm of the gamma function, with the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+fn format_time(time_ns: Option<f64>) -> String {
+    let Some(time_ns) = time_ns else {
+        return "-".into();
+    };
+    match time_ns {
+        t if t >= 1e9 => format!("{:.2}s", t / 1e9),
+        t if t >= 1e6 => format!("{:.2}ms", t / 1e6),
+        t if t >= 1e3 => format!("{:.2}µs", t / 1e3),
+        t => format!("{t:.2}ns"),
+    }
+}
+
+fn format_change(change: Option<f64>) -> String {
+    change.map_or("-".into(), |change| format!("{:+.2}%", change * 100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::walltime_results::{
+        BenchmarkMetadata, Creator, UnmeasuredBenchmark, WalltimeBenchmark,
+    };
+    use tempfile::TempDir;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{actual} isn't close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_student_t_p_value() {
+        assert_close(student_t_p_value(0.0, 10.0), 1.0);
+        assert_close(student_t_p_value(2.0, 10.0), 0.073_388_034);
+        assert_close(student_t_p_value(-2.0, 10.0), 0.073_388_034);
+        assert_close(student_t_p_value(1.0, 1.0), 0.5);
+        // Converges to the normal distribution
+        assert_close(student_t_p_value(1.959_963_985, 1e9), 0.05);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln());
+    }
+
+    /// Writes a profile folder with a benchmark per `(uri, times per round)`, and the `failed`
+    /// benchmarks.
+    fn profile_dir(benchmarks: &[(&str, Vec<u64>)], failed: &[&str]) -> TempDir {
+        let profile_dir = TempDir::new().unwrap();
+        let results_dir = profile_dir.path().join("results");
+        std::fs::create_dir(&results_dir).unwrap();
+
+        let benchmarks = benchmarks
+            .iter()
+            .map(|(uri, times)| {
+                WalltimeBenchmark::from_runtime_data(
+                    uri.to_string(),
+                    uri.to_string(),
+                    &vec![1; times.len()],
+                    times,
+                    0,
+                    None,
+                )
+            })
+            .collect();
+        let creator = Creator {
+            name: "codspeed-go".into(),
+            version: "1.0.0".into(),
+            pid: 1,
+        };
+        let mut results = WalltimeResults::new(benchmarks, creator).unwrap();
+        results.unmeasured_benchmarks = failed
+            .iter()
+            .map(|uri| UnmeasuredBenchmark {
+                metadata: BenchmarkMetadata::new(uri.to_string(), uri.to_string()),
+                status: BenchmarkStatus::Failed,
+                message: Some("--- FAIL".into()),
+            })
+            .collect();
+        std::fs::write(
+            results_dir.join("1.json"),
+            serde_json::to_string(&results).unwrap(),
+        )
+        .unwrap();
+        profile_dir
+    }
+
+    /// 100 rounds around `time_ns`, with some noise.
+    fn rounds(time_ns: u64) -> Vec<u64> {
+        (0..100).map(|i| time_ns + (i * 7) % 20).collect()
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = profile_dir(
+            &[
+                ("regressed", rounds(1000)),
+                ("improved", rounds(1000)),
+                ("unchanged", rounds(1000)),
+                ("below_threshold", rounds(1000)),
+                ("removed", rounds(1000)),
+                ("failed", rounds(1000)),
+                ("single_round", rounds(1000)),
+            ],
+            &[],
+        );
+        let candidate = profile_dir(
+            &[
+                ("regressed", rounds(1200)),
+                ("improved", rounds(500)),
+                ("unchanged", rounds(1000)),
+                ("below_threshold", rounds(1020)),
+                ("added", rounds(1000)),
+                ("single_round", vec![2000]),
+            ],
+            &["failed", "failed_new"],
+        );
+
+        let comparison = compare(
+            baseline.path(),
+            candidate.path(),
+            &CompareOptions::default(),
+        )
+        .unwrap();
+        let verdicts: Vec<_> = comparison
+            .benchmarks
+            .iter()
+            .map(|benchmark| (benchmark.uri.as_str(), benchmark.verdict))
+            .collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                ("regressed", Verdict::Regressed),
+                ("failed", Verdict::Failed),
+                ("failed_new", Verdict::Failed),
+                ("improved", Verdict::Improved),
+                // Without a p-value, despite the change
+                ("single_round", Verdict::Inconclusive),
+                ("below_threshold", Verdict::Unchanged),
+                ("unchanged", Verdict::Unchanged),
+                ("added", Verdict::Added),
+                ("removed", Verdict::Removed),
+            ]
+        );
+        assert!(comparison.has_regressions());
+
+        let regressed = &comparison.benchmarks[0];
+        assert_close(regressed.mean_change.unwrap(), 200. / 1009.5);
+        assert!(regressed.p_value.unwrap() < 1e-6);
+        assert_eq!(comparison.benchmarks[4].p_value, None);
+        // The change is significant, but below the threshold
+        assert!(comparison.benchmarks[5].p_value.unwrap() < 0.05);
+        assert_eq!(comparison.benchmarks[6].p_value, Some(1.0));
+
+        let table = comparison.table();
+        assert!(table.starts_with("Benchmark "), "{table}");
+        assert!(table.contains("+19.81%"), "{table}");
+        assert!(table.ends_with(
+            "1 regressed, 2 failed, 1 improved, 1 inconclusive, 2 unchanged, 1 added, 1 removed\n"
+        ));
+    }
+
+    #[test]
+    fn test_compare_without_regressions() {
+        let baseline = profile_dir(&[("a", rounds(1000))], &[]);
+        let candidate = profile_dir(&[("a", rounds(1030))], &[]);
+
+        // Results folders can be given directly
+        let comparison = compare(
+            &baseline.path().join("results"),
+            candidate.path(),
+            &CompareOptions::default(),
+        )
+        .unwrap();
+        assert!(!comparison.has_regressions());
+
+        let options = CompareOptions {
+            threshold: 0.01,
+            ..Default::default()
+        };
+        let comparison = compare(baseline.path(), candidate.path(), &options).unwrap();
+        assert!(comparison.has_regressions());
+    }
+
+    #[test]
+    fn test_compare_without_results() {
+        let baseline = profile_dir(&[("a", rounds(1000))], &[]);
+        let empty = TempDir::new().unwrap();
+        let error = compare(baseline.path(), empty.path(), &CompareOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("No results found"), "{error}");
+    }
+}
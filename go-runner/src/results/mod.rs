@@ -1,2 +1,11 @@
+pub mod archive;
+pub mod compare;
+pub mod raw_log;
 pub mod raw_result;
+pub mod raw_rounds;
+pub mod run_metadata;
+pub mod schema;
+pub mod stability;
+pub mod stats;
+pub mod summary;
 pub mod walltime_results;
@@ -0,0 +1,179 @@
+//! Raw results log, appended by every benchmark process to `raw_results/<pid>.log` (see
+//! `appendRawResults` in the overlay), rather than writing a file per benchmark.
+//!
+//! Every frame is the length of a raw result JSON in bytes, padded to 20 digits, on its own line,
+//! followed by the JSON and a newline:
+//! ```text
+//! 00000000000000000093
+//! {"schema_version":1,"name":"BenchmarkFoo",...}
+//! ```
+//! The length detects a frame truncated by a crash, and allows to read the raw results one frame
+//! at a time, without loading the whole log in memory.
+
+use crate::prelude::*;
+use crate::results::raw_result::RawResult;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Extension of the raw results logs.
+pub const EXTENSION: &str = "log";
+
+/// Length of the frame header: 20 digits and a newline.
+const HEADER_LEN: u64 = 21;
+
+/// Returns the raw results of a log, in order. A truncated or malformed frame header ends the
+/// log, since the next frames can't be found anymore.
+pub fn frames(path: &Path) -> Result<Frames> {
+    let file = File::open(path).context("Failed to open file")?;
+    let len = file
+        .metadata()
+        .context("Failed to read file metadata")?
+        .len();
+    Ok(Frames {
+        file,
+        len,
+        offset: 0,
+        done: false,
+    })
+}
+
+/// Iterator over the raw results of a log, see [`frames`].
+pub struct Frames {
+    file: File,
+    len: u64,
+    offset: u64,
+    done: bool,
+}
+
+impl Frames {
+    /// Reads the header of the frame at `offset`, and returns the length of its raw result.
+    fn read_header(&self) -> Result<u64> {
+        if self.offset + HEADER_LEN > self.len {
+            bail!("Truncated frame header");
+        }
+
+        let mut header = [0; HEADER_LEN as usize];
+        (&self.file).seek(SeekFrom::Start(self.offset))?;
+        (&self.file).read_exact(&mut header)?;
+        let (digits, newline) = header.split_at(HEADER_LEN as usize - 1);
+        let frame_len = std::str::from_utf8(digits)
+            .ok()
+            .filter(|_| newline == b"\n")
+            .and_then(|digits| digits.parse::<u64>().ok())
+            .with_context(|| {
+                format!(
+                    "Invalid frame header {:?}",
+                    String::from_utf8_lossy(&header)
+                )
+            })?;
+
+        let end = self.offset + HEADER_LEN + frame_len;
+        if end + 1 > self.len {
+            bail!(
+                "Truncated frame, expected {frame_len} bytes but only {} are left",
+                self.len - self.offset - HEADER_LEN
+            );
+        }
+        Ok(frame_len)
+    }
+
+    /// Parses the raw result of the frame starting at `start`, streamed from the file.
+    fn read_frame(&self, start: u64, frame_len: u64) -> Result<RawResult> {
+        RawResult::parse_reader(|| {
+            (&self.file).seek(SeekFrom::Start(start))?;
+            Ok(BufReader::new((&self.file).take(frame_len)))
+        })
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<RawResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset >= self.len {
+            return None;
+        }
+
+        let frame_len = match self.read_header() {
+            Ok(frame_len) => frame_len,
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
+            }
+        };
+
+        let start = self.offset + HEADER_LEN;
+        self.offset = start + frame_len + 1;
+        Some(self.read_frame(start, frame_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const RAW_RESULT: &str = r#"{"schema_version":1,"name":"BenchmarkFoo","uri":"foo_test.go::BenchmarkFoo","pid":1,"codspeed_time_per_round_ns":[1000],"codspeed_iters_per_round":[1]}"#;
+
+    fn frame(json: &str) -> String {
+        format!("{:020}\n{json}\n", json.len())
+    }
+
+    fn write_log(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_frames() {
+        let second = RAW_RESULT.replace("BenchmarkFoo", "BenchmarkBar");
+        let log = write_log(&(frame(RAW_RESULT) + &frame(&second)));
+
+        let names: Vec<_> = frames(log.path())
+            .unwrap()
+            .map(|raw_result| raw_result.unwrap().metadata.name)
+            .collect();
+        assert_eq!(names, vec!["BenchmarkFoo", "BenchmarkBar"]);
+    }
+
+    #[test]
+    fn test_invalid_frame_is_skipped() {
+        let log = write_log(&(frame(r#"{"name": 1}"#) + &frame(RAW_RESULT)));
+
+        let parsed: Vec<_> = frames(log.path()).unwrap().collect();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].is_err());
+        assert_eq!(parsed[1].as_ref().unwrap().metadata.name, "BenchmarkFoo");
+    }
+
+    #[test]
+    fn test_truncated_frame() {
+        let truncated = frame(RAW_RESULT);
+        let log = write_log(&(frame(RAW_RESULT) + &truncated[..truncated.len() - 10]));
+
+        let parsed: Vec<_> = frames(log.path()).unwrap().collect();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].is_ok());
+        let error = parsed[1].as_ref().unwrap_err().to_string();
+        assert!(error.starts_with("Truncated frame, expected"), "{error}");
+
+        let log = write_log(&(frame(RAW_RESULT) + "0000"));
+        let parsed: Vec<_> = frames(log.path()).unwrap().collect();
+        assert_eq!(
+            parsed[1].as_ref().unwrap_err().to_string(),
+            "Truncated frame header"
+        );
+    }
+
+    #[test]
+    fn test_invalid_frame_header_ends_the_log() {
+        let log = write_log(&("not a header\n".repeat(2) + &frame(RAW_RESULT)));
+
+        let parsed: Vec<_> = frames(log.path()).unwrap().collect();
+        assert_eq!(parsed.len(), 1);
+        let error = parsed[0].as_ref().unwrap_err().to_string();
+        assert!(error.starts_with("Invalid frame header"), "{error}");
+    }
+}
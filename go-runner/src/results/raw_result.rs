@@ -1,52 +1,492 @@
+use crate::prelude::*;
 use rayon::prelude::*;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
 
-use crate::results::walltime_results::WalltimeBenchmark;
+use crate::config::{ResultsConfig, StatsConfig};
+use crate::results::raw_log;
+use crate::results::raw_rounds::{self, RoundsField, RoundsParser, RoundsSeed, SumSeed};
+use crate::results::stats::RoundStats;
+use crate::results::walltime_results::{
+    BenchmarkMetadata, BenchmarkStatus, Parallelism, Truncation, UnmeasuredBenchmark,
+    WalltimeBenchmark,
+};
 
-// WARN: Keep in sync with Golang "testing" fork (benchmark.go)
+/// Effective round strategy of a benchmark.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RawRoundConfig {
+    pub max_rounds: u64,
+    /// Time of a round at the speed estimated by the warmup, which isn't enforced. Written as
+    /// `min_round_time_ns` by older overlays.
+    #[serde(alias = "min_round_time_ns")]
+    pub target_round_time_ns: u64,
+    pub max_time_ns: u64,
+}
+
+/// Raw results files parsed at the same time, each of them buffering up to one of its rounds
+/// arrays (see [`raw_rounds`]).
+const MAX_FILES_IN_FLIGHT: usize = 8;
+
+const PARALLEL_OPS_FIELD: &str = "codspeed_parallel_ops_per_round";
+
+// WARN: Keep in sync with Golang "testing" fork (codspeed.go), and bump the schema version (see
+// `RawResult::SCHEMA_VERSION`) when the format changes.
+/// Fields of a raw result, other than the arrays with a value per round, which are streamed while
+/// parsing (see [`RawResult`]).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RawResult {
+pub struct RawResultMetadata {
+    /// See [`RawResult::SCHEMA_VERSION`].
+    #[serde(default)]
+    pub schema_version: u32,
+
     pub name: String,
     pub uri: String,
     pub pid: u32,
-    pub codspeed_time_per_round_ns: Vec<u64>,
-    pub codspeed_iters_per_round: Vec<u64>,
+
+    /// Where the benchmark function is defined, see `BenchmarkMetadata`.
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(default)]
+    pub package: Option<String>,
+    #[serde(default)]
+    pub function: Option<String>,
+
+    /// Benchmarks that failed or were skipped don't have any rounds.
+    #[serde(default)]
+    pub status: BenchmarkStatus,
+    /// Output of the benchmark, when it failed or was skipped.
+    #[serde(default)]
+    pub message: Option<String>,
+
+    /// Bytes processed per iteration, set with `b.SetBytes`.
+    #[serde(default)]
+    pub bytes_per_op: u64,
+
+    /// Iterations and wall time of the warmup phase, which aren't part of the rounds.
+    #[serde(default)]
+    pub codspeed_warmup_iters: u64,
+    #[serde(default)]
+    pub codspeed_warmup_time_ns: u64,
+
+    /// Only available for benchmarks that are split into rounds by the runner (i.e. not for
+    /// `b.Loop()` benchmarks or a fixed iteration count).
+    #[serde(default)]
+    pub codspeed_round_config: Option<RawRoundConfig>,
+
+    /// Set when the benchmark was stopped before executing all the planned rounds.
+    #[serde(default)]
+    pub codspeed_truncation: Option<Truncation>,
+
+    /// Only available for `b.RunParallel` benchmarks.
+    #[serde(default)]
+    pub codspeed_parallelism: Option<Parallelism>,
+
+    /// Metrics reported with `b.ReportMetric`, by unit.
+    #[serde(default)]
+    pub extra: BTreeMap<String, f64>,
+}
+
+/// Raw result of a benchmark. The arrays with a value per round, which can hold millions of
+/// values, aren't materialized: they're accumulated while parsing.
+#[derive(Debug)]
+pub struct RawResult {
+    pub metadata: RawResultMetadata,
+    /// Rounds of `codspeed_time_per_round_ns` and `codspeed_iters_per_round`, see [`raw_rounds`].
+    pub rounds: RoundStats,
+    /// Total of `codspeed_parallel_ops_per_round`, the operations executed by the `b.RunParallel`
+    /// goroutines in each round.
+    pub parallel_ops: u64,
+}
+
+impl<'de> Deserialize<'de> for RawResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(RawResultVisitor)
+    }
+}
+
+/// Streams the arrays with a value per round, and parses the other fields as a
+/// [`RawResultMetadata`].
+struct RawResultVisitor;
+
+impl<'de> Visitor<'de> for RawResultVisitor {
+    type Value = RawResult;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a raw result")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawResult, A::Error> {
+        let mut rounds = RoundsParser::default();
+        let mut parallel_ops = 0;
+        let mut fields = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let field = match key.as_str() {
+                raw_rounds::TIMES_FIELD => RoundsField::Times,
+                raw_rounds::ITERS_FIELD => RoundsField::Iters,
+                PARALLEL_OPS_FIELD => {
+                    parallel_ops = map.next_value_seed(SumSeed)?;
+                    continue;
+                }
+                _ => {
+                    fields.insert(key, map.next_value()?);
+                    continue;
+                }
+            };
+            map.next_value_seed(RoundsSeed {
+                parser: &mut rounds,
+                field,
+            })?;
+        }
+
+        let metadata =
+            serde_json::from_value(serde_json::Value::Object(fields)).map_err(de::Error::custom)?;
+        Ok(RawResult {
+            metadata,
+            rounds: rounds.finish()?,
+            parallel_ops,
+        })
+    }
+}
+
+/// Schema version of a raw result, parsed on its own when the raw result can't be parsed. The other
+/// fields are skipped without being allocated.
+#[derive(Deserialize)]
+struct Versioned {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+/// Raw result written with a schema version that isn't supported by this go-runner, which means
+/// that the overlay and the go-runner are out of sync.
+#[derive(Debug)]
+pub struct UnsupportedSchemaVersion(pub u32);
+
+impl std::fmt::Display for UnsupportedSchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unsupported raw result schema version {} (supported: 0 to {}), the raw results were written by a different version of the go-runner",
+            self.0,
+            RawResult::SCHEMA_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedSchemaVersion {}
+
+/// Benchmark parsed from a raw result.
+#[derive(Debug)]
+pub enum ParsedBenchmark {
+    Measured(Box<WalltimeBenchmark>),
+    Unmeasured(UnmeasuredBenchmark),
+}
+
+/// Raw result file that couldn't be parsed.
+#[derive(Debug)]
+pub struct InvalidRawResult {
+    pub path: PathBuf,
+    /// Where the file was moved to, see [`RawResult::QUARANTINE_DIR`].
+    pub quarantined_path: Option<PathBuf>,
+    pub error: String,
+}
+
+impl std::fmt::Display for InvalidRawResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.path, self.error)?;
+        if let Some(quarantined_path) = &self.quarantined_path {
+            write!(f, " (moved to {quarantined_path:?})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Benchmark parsed from a raw results file, with the pid of the process and the file.
+type ParsedRawResult = Result<(u32, ParsedBenchmark, PathBuf), InvalidRawResult>;
+
+/// Raw results of a folder.
+#[derive(Debug, Default)]
+pub struct ParsedFolder {
+    /// Benchmarks, with the pid of the process that executed them.
+    pub benchmarks: Vec<(u32, ParsedBenchmark)>,
+    /// Files that were parsed, by pid. They only exist anymore when they were kept, until they
+    /// are archived.
+    pub files: BTreeMap<u32, Vec<PathBuf>>,
+    pub invalid: Vec<InvalidRawResult>,
 }
 
 impl RawResult {
+    /// Folder, inside of the raw results folder, where the files that couldn't be parsed are
+    /// moved to. They're kept for debugging, rather than deleted like the parsed files.
+    pub const QUARANTINE_DIR: &str = "quarantine";
+
+    /// Version of the raw results written by the overlay (`codspeedRawResultsVersion`). Raw
+    /// results without a version were written before it was introduced, and are parsed as
+    /// version 0.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Parses a raw result file, see [`RawResult::parse_reader`].
+    pub fn parse_file(path: &Path) -> anyhow::Result<Self> {
+        Self::parse_reader(|| {
+            let file = File::open(path).context("Failed to open file")?;
+            Ok(BufReader::new(file))
+        })
+    }
+
+    /// Parses a raw result held in memory, see [`RawResult::parse_reader`].
+    pub fn parse(content: &[u8]) -> anyhow::Result<Self> {
+        Self::parse_reader(|| Ok(content))
+    }
+
+    /// Parses a raw result according to its schema version, streaming its rounds from the reader
+    /// (see [`raw_rounds`]) so that the memory doesn't grow with the size of the file. Fails with
+    /// [`UnsupportedSchemaVersion`] if the version is unknown.
+    ///
+    /// `open` returns a reader of the raw result. It's called a second time when the raw result
+    /// is invalid, to parse its version on its own.
+    pub fn parse_reader<R: Read>(
+        mut open: impl FnMut() -> anyhow::Result<R>,
+    ) -> anyhow::Result<Self> {
+        let result = serde_json::from_reader(open()?);
+        Self::check_schema_version(result, || Ok(serde_json::from_reader(open()?)?))
+    }
+
+    /// Checks the schema version of a parsed raw result. All the supported versions can be parsed
+    /// as a `RawResult`, so the version only needs to be parsed on its own (with
+    /// `parse_versioned`) when the raw result couldn't be parsed, to tell an unsupported version
+    /// apart from an invalid file.
+    fn check_schema_version(
+        result: serde_json::Result<Self>,
+        parse_versioned: impl FnOnce() -> anyhow::Result<Versioned>,
+    ) -> anyhow::Result<Self> {
+        let schema_version = match &result {
+            Ok(raw_result) => raw_result.metadata.schema_version,
+            Err(_) => parse_versioned().map_or(0, |versioned| versioned.schema_version),
+        };
+        match schema_version {
+            // Version 0 only lacks fields that are optional in version 1
+            0 | Self::SCHEMA_VERSION => result.context("Failed to parse JSON"),
+            version => Err(UnsupportedSchemaVersion(version).into()),
+        }
+    }
+
+    /// Parses all the raw results of a folder, written either to a file per benchmark or to a
+    /// log per process (see [`raw_log`]). At most [`MAX_FILES_IN_FLIGHT`] files are parsed at the
+    /// same time. Once all of them are parsed, the valid files are deleted, unless
+    /// [`ResultsConfig::keep_raw_results`] is set.
+    ///
+    /// Raw results that can't be parsed are reported in [`ParsedFolder::invalid`], but one with an
+    /// unsupported schema version fails the whole folder, since none of the raw results can be
+    /// trusted then. No file is deleted in that case, so that they can be collected again.
     pub fn parse_folder<P: AsRef<Path>>(
         folder: P,
-    ) -> anyhow::Result<Vec<(u32, WalltimeBenchmark)>> {
-        let glob_pattern = folder.as_ref().join("*.json");
-        let result = glob::glob(&glob_pattern.to_string_lossy())?
-            .par_bridge()
-            .filter_map(Result::ok)
-            .filter_map(|path| {
-                let (pid, bench) = {
-                    let file = std::fs::File::open(&path).ok()?;
-                    let reader = std::io::BufReader::new(file);
-                    let json: Self = serde_json::from_reader(reader).ok()?;
-
-                    (
-                        json.pid,
-                        WalltimeBenchmark::from_runtime_data(
-                            json.name,
-                            json.uri,
-                            &json.codspeed_iters_per_round,
-                            &json.codspeed_time_per_round_ns,
-                            None,
-                        ),
-                    )
-                };
-
-                // Remove the file since we processed it
-                std::fs::remove_file(path).ok();
-
-                Some((pid, bench))
+        config: &ResultsConfig,
+    ) -> anyhow::Result<ParsedFolder> {
+        let folder = folder.as_ref();
+        let mut paths = Vec::new();
+        for extension in ["json", raw_log::EXTENSION] {
+            let glob_pattern = folder.join(format!("*.{extension}"));
+            paths.extend(glob::glob(&glob_pattern.to_string_lossy())?);
+        }
+
+        // Every thread of the pool parses a single file at a time
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(MAX_FILES_IN_FLIGHT.min(rayon::current_num_threads()))
+            .build()?;
+        let results: Vec<Vec<ParsedRawResult>> = pool.install(|| {
+            paths
+                .into_par_iter()
+                .map(|entry| {
+                    let path = match entry {
+                        Ok(path) => path,
+                        Err(error) => {
+                            return Ok(vec![Err(InvalidRawResult {
+                                path: error.path().to_path_buf(),
+                                quarantined_path: None,
+                                error: error.error().to_string(),
+                            })]);
+                        }
+                    };
+
+                    if path
+                        .extension()
+                        .is_some_and(|ext| ext == raw_log::EXTENSION)
+                    {
+                        Self::parse_log(folder, path, config)
+                    } else {
+                        Self::parse_json(folder, path, config).map(|result| vec![result])
+                    }
+                })
+                .collect::<anyhow::Result<_>>()
+        })?;
+
+        let mut parsed = ParsedFolder::default();
+        for result in results.into_iter().flatten() {
+            match result {
+                Ok((pid, benchmark, path)) => {
+                    parsed.benchmarks.push((pid, benchmark));
+                    parsed.files.entry(pid).or_default().push(path);
+                }
+                Err(invalid) => parsed.invalid.push(invalid),
+            }
+        }
+        parsed.files.values_mut().for_each(|files| {
+            files.sort();
+            files.dedup();
+        });
+        parsed.invalid.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if !config.keep_raw_results {
+            // Logs with invalid frames are quarantined (or kept if that failed) rather than deleted
+            let invalid: BTreeSet<_> = parsed.invalid.iter().map(|invalid| &invalid.path).collect();
+            for path in parsed.files.values().flatten() {
+                if !invalid.contains(path)
+                    && let Err(error) = std::fs::remove_file(path)
+                {
+                    warn!("Failed to remove raw result {path:?}: {error}");
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Parses a file containing the raw result of a single benchmark.
+    fn parse_json(
+        folder: &Path,
+        path: PathBuf,
+        config: &ResultsConfig,
+    ) -> anyhow::Result<ParsedRawResult> {
+        match Self::parse_file(&path) {
+            Ok(json) => Ok(Ok((
+                json.metadata.pid,
+                json.into_benchmark(&config.stats),
+                path,
+            ))),
+            Err(error) if error.is::<UnsupportedSchemaVersion>() => {
+                Err(error.context(format!("Failed to parse raw result {path:?}")))
+            }
+            Err(error) => Ok(Err(InvalidRawResult {
+                quarantined_path: quarantine(folder, &path, false),
+                path,
+                error: format!("{error:#}"),
+            })),
+        }
+    }
+
+    /// Parses the raw results log of a process. The valid frames are used even if some others
+    /// are invalid, in which case the log is quarantined as a whole.
+    fn parse_log(
+        folder: &Path,
+        path: PathBuf,
+        config: &ResultsConfig,
+    ) -> anyhow::Result<Vec<ParsedRawResult>> {
+        let mut results = Vec::new();
+        let mut invalid_frames = Vec::new();
+        match raw_log::frames(&path) {
+            Ok(frames) => {
+                for (index, frame) in frames.enumerate() {
+                    match frame {
+                        // Convert every raw result right away, so that only one is in memory
+                        Ok(json) => {
+                            results.push((json.metadata.pid, json.into_benchmark(&config.stats)))
+                        }
+                        Err(error) if error.is::<UnsupportedSchemaVersion>() => {
+                            return Err(error.context(format!(
+                                "Failed to parse frame {index} of raw results log {path:?}"
+                            )));
+                        }
+                        Err(error) => invalid_frames.push(format!("frame {index}: {error:#}")),
+                    }
+                }
+            }
+            Err(error) => invalid_frames.push(format!("{error:#}")),
+        }
+
+        let quarantined_path = if invalid_frames.is_empty() {
+            None
+        } else {
+            // The original is only kept when its valid frames are archived, which removes it
+            quarantine(
+                folder,
+                &path,
+                config.keep_raw_results && !results.is_empty(),
+            )
+        };
+
+        let invalid = invalid_frames.into_iter().map(|error| {
+            Err(InvalidRawResult {
+                path: path.clone(),
+                quarantined_path: quarantined_path.clone(),
+                error,
             })
-            .collect();
-        Ok(result)
+        });
+        Ok(results
+            .into_iter()
+            .map(|(pid, benchmark)| Ok((pid, benchmark, path.clone())))
+            .chain(invalid)
+            .collect())
+    }
+
+    fn into_benchmark(self, config: &StatsConfig) -> ParsedBenchmark {
+        let RawResult {
+            metadata: json,
+            rounds,
+            parallel_ops,
+        } = self;
+        // Failed and skipped benchmarks don't have any (complete) rounds to compute stats from
+        if json.status != BenchmarkStatus::Ok {
+            return ParsedBenchmark::Unmeasured(UnmeasuredBenchmark {
+                metadata: BenchmarkMetadata::new(json.name, json.uri).with_source(
+                    json.line,
+                    json.package,
+                    json.function,
+                ),
+                status: json.status,
+                message: json.message.filter(|message| !message.is_empty()),
+            });
+        }
+
+        ParsedBenchmark::Measured(Box::new(
+            WalltimeBenchmark::from_round_stats(
+                json.name,
+                json.uri,
+                rounds.with_config(*config),
+                json.bytes_per_op,
+                None,
+            )
+            .with_warmup(json.codspeed_warmup_iters, json.codspeed_warmup_time_ns)
+            .with_round_config(json.codspeed_round_config)
+            .with_source(json.line, json.package, json.function)
+            .with_truncation(json.codspeed_truncation)
+            .with_parallelism(json.codspeed_parallelism, parallel_ops)
+            .with_custom_metrics(json.extra),
+        ))
+    }
+}
+
+/// Moves (or copies, with `keep_original`) a raw result that couldn't be parsed to the quarantine
+/// folder, and returns its new path.
+fn quarantine(folder: &Path, path: &Path, keep_original: bool) -> Option<PathBuf> {
+    let quarantine_dir = folder.join(RawResult::QUARANTINE_DIR);
+    let quarantined_path = quarantine_dir.join(path.file_name()?);
+    let result = std::fs::create_dir_all(&quarantine_dir).and_then(|_| {
+        if keep_original {
+            std::fs::copy(path, &quarantined_path).map(|_| ())
+        } else {
+            std::fs::rename(path, &quarantined_path)
+        }
+    });
+    match result {
+        Ok(()) => Some(quarantined_path),
+        Err(error) => {
+            warn!("Failed to quarantine raw result {path:?}: {error}");
+            None
+        }
     }
 }
 
@@ -64,10 +504,382 @@ mod tests {
     "codspeed_iters_per_round": [1, 2, 3]
 }"#;
         let result: RawResult = serde_json::from_str(json_data).unwrap();
+        assert_eq!(result.parallel_ops, 0);
+
+        let metadata = &result.metadata;
+        assert_eq!(metadata.name, "BenchmarkFibonacci20-16");
+        assert_eq!(metadata.pid, 777767);
+        assert!(metadata.extra.is_empty());
+        assert_eq!(metadata.bytes_per_op, 0);
+        assert_eq!(metadata.codspeed_warmup_iters, 0);
+        assert_eq!(metadata.codspeed_warmup_time_ns, 0);
+        assert!(metadata.codspeed_round_config.is_none());
+        assert!(metadata.codspeed_truncation.is_none());
+        assert!(metadata.codspeed_parallelism.is_none());
+        assert!(metadata.line.is_none());
+        assert!(metadata.package.is_none());
+        assert!(metadata.function.is_none());
+        assert_eq!(metadata.status, BenchmarkStatus::Ok);
+
+        let ParsedBenchmark::Measured(benchmark) = result.into_benchmark(&StatsConfig::default())
+        else {
+            panic!("The benchmark should be measured");
+        };
+        assert_eq!(benchmark.stats().rounds(), 3);
+        assert_eq!(benchmark.stats().mean_ns, 1000.0);
+    }
+
+    #[test]
+    fn test_raw_result_with_the_iterations_first() {
+        let json_data = r#"{
+    "name": "BenchmarkFoo",
+    "uri": "foo_test.go::BenchmarkFoo",
+    "codspeed_iters_per_round": [1, 2, 4],
+    "pid": 1,
+    "codspeed_time_per_round_ns": [1000, 4000, 4000]
+}"#;
+        let result = RawResult::parse(json_data.as_bytes()).unwrap();
+        let stats = result.rounds.finish();
+        assert_eq!(stats.rounds, 3);
+        assert_eq!(stats.min_ns, 1000.0);
+        assert_eq!(stats.max_ns, 2000.0);
+    }
+
+    #[test]
+    fn test_raw_result_without_rounds() {
+        let json_data = r#"{
+    "name": "BenchmarkFoo",
+    "uri": "foo_test.go::BenchmarkFoo",
+    "pid": 1,
+    "codspeed_time_per_round_ns": [1000]
+}"#;
+        let error = RawResult::parse(json_data.as_bytes()).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("missing field `codspeed_iters_per_round`"),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn test_raw_result_deserialization_with_optional_fields() {
+        let json_data = r#"{
+    "name": "BenchmarkQueue",
+    "uri": "pkg/queue_test.go::BenchmarkQueue",
+    "pid": 777767,
+    "line": 42,
+    "package": "example.com/queue",
+    "function": "example.com/queue_test.BenchmarkQueue",
+    "codspeed_time_per_round_ns": [1000],
+    "codspeed_iters_per_round": [1],
+    "extra": {"msgs/s": 1234.5, "allocs/op": 2},
+    "bytes_per_op": 1024,
+    "codspeed_round_config": {"max_rounds": 100, "target_round_time_ns": 30000000, "max_time_ns": 9000000000},
+    "codspeed_truncation": {"reason": "max_time_exceeded", "planned_rounds": 100, "actual_rounds": 12},
+    "codspeed_parallelism": {"goroutines": 16, "parallelism": 2, "gomaxprocs": 8},
+    "codspeed_parallel_ops_per_round": [1]
+}"#;
+        let result: RawResult = serde_json::from_str(json_data).unwrap();
+        assert_eq!(result.parallel_ops, 1);
+
+        let metadata = result.metadata;
+        assert_eq!(metadata.line, Some(42));
+        assert_eq!(metadata.package.as_deref(), Some("example.com/queue"));
+        assert_eq!(
+            metadata.function.as_deref(),
+            Some("example.com/queue_test.BenchmarkQueue")
+        );
+        assert_eq!(metadata.extra["msgs/s"], 1234.5);
+        assert_eq!(metadata.extra["allocs/op"], 2.0);
+        assert_eq!(metadata.bytes_per_op, 1024);
+        assert_eq!(metadata.codspeed_round_config.unwrap().max_rounds, 100);
+        assert_eq!(metadata.codspeed_truncation.unwrap().actual_rounds, 12);
+        assert_eq!(metadata.codspeed_parallelism.unwrap().goroutines, 16);
+    }
+
+    #[test]
+    fn test_raw_result_failed() {
+        let json_data = r#"{
+    "name": "BenchmarkBroken",
+    "uri": "pkg/foo/broken_test.go::BenchmarkBroken",
+    "pid": 777767,
+    "status": "failed",
+    "message": "broken_test.go:12: unexpected result",
+    "codspeed_time_per_round_ns": [],
+    "codspeed_iters_per_round": []
+}"#;
+        let result: RawResult = serde_json::from_str(json_data).unwrap();
+
+        let ParsedBenchmark::Unmeasured(benchmark) = result.into_benchmark(&StatsConfig::default())
+        else {
+            panic!("Failed benchmarks shouldn't be measured");
+        };
+        assert_eq!(benchmark.status, BenchmarkStatus::Failed);
+        assert_eq!(
+            benchmark.message.as_deref(),
+            Some("broken_test.go:12: unexpected result")
+        );
+    }
+
+    #[test]
+    fn test_raw_result_skipped_without_message() {
+        let json_data = r#"{
+    "name": "BenchmarkSkipped",
+    "uri": "pkg/foo/skip_test.go::BenchmarkSkipped",
+    "pid": 777767,
+    "status": "skipped",
+    "message": "",
+    "codspeed_time_per_round_ns": [],
+    "codspeed_iters_per_round": []
+}"#;
+        let result: RawResult = serde_json::from_str(json_data).unwrap();
+
+        let ParsedBenchmark::Unmeasured(benchmark) = result.into_benchmark(&StatsConfig::default())
+        else {
+            panic!("Skipped benchmarks shouldn't be measured");
+        };
+        assert_eq!(benchmark.status, BenchmarkStatus::Skipped);
+        assert!(benchmark.message.is_none());
+    }
+
+    #[test]
+    fn test_parse_folder_quarantines_invalid_files() {
+        let folder = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            folder.path().join("valid.json"),
+            r#"{
+    "name": "BenchmarkFoo",
+    "uri": "foo_test.go::BenchmarkFoo",
+    "pid": 1,
+    "codspeed_time_per_round_ns": [1000],
+    "codspeed_iters_per_round": [1]
+}"#,
+        )
+        .unwrap();
+        std::fs::write(folder.path().join("truncated.json"), r#"{"name": "Benchm"#).unwrap();
+        std::fs::write(folder.path().join("invalid.json"), r#"{"name": 1}"#).unwrap();
+
+        let parsed = RawResult::parse_folder(folder.path(), &ResultsConfig::default()).unwrap();
+        assert_eq!(parsed.benchmarks.len(), 1);
+        assert_eq!(parsed.benchmarks[0].0, 1);
+        assert!(!folder.path().join("valid.json").exists());
+
+        assert_eq!(parsed.invalid.len(), 2);
+        let quarantine_dir = folder.path().join(RawResult::QUARANTINE_DIR);
+        for (invalid, file_name) in parsed
+            .invalid
+            .iter()
+            .zip(["invalid.json", "truncated.json"])
+        {
+            assert_eq!(invalid.path, folder.path().join(file_name));
+            assert_eq!(
+                invalid.quarantined_path.as_deref(),
+                Some(quarantine_dir.join(file_name).as_path())
+            );
+            assert!(!invalid.path.exists());
+            assert!(quarantine_dir.join(file_name).exists());
+            assert!(
+                invalid.error.starts_with("Failed to parse JSON: "),
+                "{}",
+                invalid.error
+            );
+        }
+        assert!(parsed.invalid[1].error.contains("EOF"));
+
+        // Quarantined files aren't parsed again
+        let parsed = RawResult::parse_folder(folder.path(), &ResultsConfig::default()).unwrap();
+        assert!(parsed.benchmarks.is_empty());
+        assert!(parsed.invalid.is_empty());
+    }
+
+    fn fixture(version: u32) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/raw_results")
+            .join(format!("v{version}.json"))
+    }
+
+    #[test]
+    fn test_parse_fixtures_of_every_version() {
+        // Every supported version must have a fixture
+        for version in 0..=RawResult::SCHEMA_VERSION {
+            let path = fixture(version);
+            let result = RawResult::parse_file(&path)
+                .unwrap_or_else(|error| panic!("Failed to parse {path:?}: {error:#}"));
+            assert_eq!(result.metadata.schema_version, version);
+            assert_eq!(result.metadata.pid, 4242);
+
+            let ParsedBenchmark::Measured(benchmark) =
+                result.into_benchmark(&StatsConfig::default())
+            else {
+                panic!("Fixture of version {version} should be measured");
+            };
+            assert!(benchmark.stats().rounds() > 0);
+        }
+    }
+
+    #[test]
+    fn test_parse_fixture_v1() {
+        let result = RawResult::parse_file(&fixture(1)).unwrap();
+        assert_eq!(result.parallel_ops, 8);
+
+        let metadata = result.metadata;
+        assert_eq!(metadata.line, Some(12));
+        assert_eq!(metadata.package.as_deref(), Some("example.com/pkg"));
+        assert_eq!(metadata.bytes_per_op, 1024);
+        assert_eq!(metadata.codspeed_parallelism.unwrap().goroutines, 8);
+        assert_eq!(metadata.extra["allocs/op"], 3.0);
+    }
+
+    #[test]
+    fn test_parse_unsupported_schema_version() {
+        let json_data = format!(
+            r#"{{"schema_version": {}, "name": "BenchmarkFoo"}}"#,
+            RawResult::SCHEMA_VERSION + 1
+        );
+        let error = RawResult::parse(json_data.as_bytes()).unwrap_err();
+        assert!(error.is::<UnsupportedSchemaVersion>());
+        assert!(
+            error
+                .to_string()
+                .starts_with("Unsupported raw result schema version 2"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_parse_folder_fails_on_unsupported_schema_version() {
+        let folder = tempfile::TempDir::new().unwrap();
+        std::fs::copy(fixture(1), folder.path().join("valid.json")).unwrap();
+        let raw_result = std::fs::read_to_string(fixture(1)).unwrap();
+        let raw_result: serde_json::Value = serde_json::from_str(&raw_result).unwrap();
+        std::fs::write(
+            folder.path().join("4242.log"),
+            log_frame(&raw_result.to_string()),
+        )
+        .unwrap();
+        std::fs::write(
+            folder.path().join("future.json"),
+            r#"{"schema_version": 1000, "name": "BenchmarkFoo"}"#,
+        )
+        .unwrap();
+
+        let error = RawResult::parse_folder(folder.path(), &ResultsConfig::default()).unwrap_err();
+        assert!(format!("{error:#}").contains("Unsupported raw result schema version 1000"));
+        // The file isn't quarantined, since it's not invalid
+        assert!(folder.path().join("future.json").exists());
+        // The valid files are kept, so that they can be collected again once the version is
+        // supported
+        assert!(folder.path().join("valid.json").exists());
+        assert!(folder.path().join("4242.log").exists());
+    }
+
+    fn log_frame(json: &str) -> String {
+        format!("{:020}\n{json}\n", json.len())
+    }
+
+    #[test]
+    fn test_parse_folder_with_raw_results_logs() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let raw_result = std::fs::read_to_string(fixture(1)).unwrap();
+        let raw_result: serde_json::Value = serde_json::from_str(&raw_result).unwrap();
+        let raw_result = raw_result.to_string();
+
+        std::fs::write(
+            folder.path().join("4242.log"),
+            log_frame(&raw_result) + &log_frame(&raw_result),
+        )
+        .unwrap();
+        let truncated = log_frame(&raw_result);
+        std::fs::write(
+            folder.path().join("4243.log"),
+            log_frame(&raw_result.replace("4242", "4243"))
+                + &log_frame(r#"{"name": 1}"#)
+                + &truncated[..truncated.len() / 2],
+        )
+        .unwrap();
+        std::fs::copy(fixture(0), folder.path().join("v0.json")).unwrap();
+
+        let parsed = RawResult::parse_folder(folder.path(), &ResultsConfig::default()).unwrap();
+        assert_eq!(parsed.benchmarks.len(), 4);
+        assert_eq!(
+            parsed.files,
+            BTreeMap::from([
+                (
+                    4242,
+                    vec![
+                        folder.path().join("4242.log"),
+                        folder.path().join("v0.json")
+                    ]
+                ),
+                (4243, vec![folder.path().join("4243.log")]),
+            ])
+        );
+        // The valid log is parsed and removed, the other one is quarantined
+        assert!(!folder.path().join("4242.log").exists());
+        assert!(!folder.path().join("4243.log").exists());
+        let quarantined_path = folder
+            .path()
+            .join(RawResult::QUARANTINE_DIR)
+            .join("4243.log");
+        assert!(quarantined_path.exists());
+
+        assert_eq!(parsed.invalid.len(), 2);
+        for invalid in &parsed.invalid {
+            assert_eq!(invalid.path, folder.path().join("4243.log"));
+            assert_eq!(
+                invalid.quarantined_path.as_deref(),
+                Some(quarantined_path.as_path())
+            );
+        }
+        assert!(
+            parsed.invalid[0]
+                .error
+                .starts_with("frame 1: Failed to parse JSON: "),
+            "{}",
+            parsed.invalid[0].error
+        );
+        assert!(
+            parsed.invalid[1]
+                .error
+                .starts_with("frame 2: Truncated frame"),
+            "{}",
+            parsed.invalid[1].error
+        );
+    }
+
+    #[test]
+    fn test_parse_folder_keeps_raw_results_logs() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let raw_result = std::fs::read_to_string(fixture(1)).unwrap();
+        let raw_result: serde_json::Value = serde_json::from_str(&raw_result).unwrap();
+        let log = folder.path().join("1.log");
+        std::fs::write(
+            &log,
+            log_frame(&raw_result.to_string()) + &log_frame(r#"{"name": 1}"#),
+        )
+        .unwrap();
+        let invalid_log = folder.path().join("2.log");
+        std::fs::write(&invalid_log, log_frame(r#"{"name": 1}"#)).unwrap();
 
-        assert_eq!(result.name, "BenchmarkFibonacci20-16");
-        assert_eq!(result.pid, 777767);
-        assert_eq!(result.codspeed_time_per_round_ns.len(), 3);
-        assert_eq!(result.codspeed_iters_per_round.len(), 3);
+        let parsed = RawResult::parse_folder(
+            folder.path(),
+            &ResultsConfig {
+                keep_raw_results: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(parsed.invalid.len(), 2);
+        assert!(
+            parsed.invalid.iter().all(|invalid| invalid
+                .quarantined_path
+                .as_ref()
+                .unwrap()
+                .exists())
+        );
+        // The partially valid log is copied to the quarantine, since its valid frames are kept
+        assert!(log.exists());
+        assert_eq!(parsed.files.values().flatten().collect::<Vec<_>>(), [&log]);
+        // The other one isn't archived, so it's moved like when the raw results aren't kept
+        assert!(!invalid_log.exists());
     }
 }
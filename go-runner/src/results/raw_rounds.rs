@@ -0,0 +1,273 @@
+//! Streaming deserialization of the rounds of a raw result.
+//!
+//! The rounds are written as two arrays, `codspeed_iters_per_round` and
+//! `codspeed_time_per_round_ns`, which can hold millions of values. Rather than materializing
+//! both of them, the array parsed first is buffered run-length encoded, and the second one is
+//! streamed into a [`RoundStats`] along with it. The overlay writes the iterations first, which are
+//! the same for most rounds, so that the buffer stays small.
+//!
+//! The buffer holds a `(value, count)` run (16 bytes) for every change of value in the first
+//! array. Raw results written by older overlays (including every version 0 raw result) have the
+//! times first, which differ for almost every round: the buffer then holds nearly the whole times
+//! array, i.e. up to 16 bytes per round, until the iterations are parsed.
+
+use crate::results::stats::RoundStats;
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use std::collections::VecDeque;
+
+pub const TIMES_FIELD: &str = "codspeed_time_per_round_ns";
+pub const ITERS_FIELD: &str = "codspeed_iters_per_round";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundsField {
+    Times,
+    Iters,
+}
+
+impl RoundsField {
+    fn name(self) -> &'static str {
+        match self {
+            RoundsField::Times => TIMES_FIELD,
+            RoundsField::Iters => ITERS_FIELD,
+        }
+    }
+}
+
+/// Pairs the values of the two rounds arrays into a [`RoundStats`], see the
+/// [module documentation](self). Like zipping the arrays, the values of the longest one that
+/// don't have a counterpart are ignored.
+#[derive(Debug, Default)]
+pub struct RoundsParser {
+    stats: RoundStats,
+    /// Array that was parsed first.
+    first: Option<RoundsField>,
+    /// Values of the first array that weren't paired yet, as `(value, count)` runs.
+    buffered: VecDeque<(u64, u64)>,
+    second_parsed: bool,
+}
+
+impl RoundsParser {
+    fn start<E: de::Error>(&mut self, field: RoundsField) -> Result<(), E> {
+        match self.first {
+            None => self.first = Some(field),
+            Some(first) if first != field && !self.second_parsed => self.second_parsed = true,
+            Some(_) => return Err(E::duplicate_field(field.name())),
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, value: u64) {
+        let Some(first) = self.first else {
+            return;
+        };
+
+        if !self.second_parsed {
+            match self.buffered.back_mut() {
+                Some((last, count)) if *last == value => *count += 1,
+                _ => self.buffered.push_back((value, 1)),
+            }
+            return;
+        }
+
+        let Some((buffered, count)) = self.buffered.front_mut() else {
+            return;
+        };
+        let buffered = *buffered;
+        *count -= 1;
+        if *count == 0 {
+            self.buffered.pop_front();
+        }
+
+        match first {
+            RoundsField::Times => self.stats.push(buffered, value),
+            RoundsField::Iters => self.stats.push(value, buffered),
+        }
+    }
+
+    /// Returns the rounds, failing if one of the arrays is missing.
+    pub fn finish<E: de::Error>(self) -> Result<RoundStats, E> {
+        match self.first {
+            None => Err(E::missing_field(TIMES_FIELD)),
+            Some(RoundsField::Times) if !self.second_parsed => Err(E::missing_field(ITERS_FIELD)),
+            Some(RoundsField::Iters) if !self.second_parsed => Err(E::missing_field(TIMES_FIELD)),
+            Some(_) => Ok(self.stats),
+        }
+    }
+}
+
+/// Parses one of the rounds arrays into a [`RoundsParser`].
+pub struct RoundsSeed<'a> {
+    pub parser: &'a mut RoundsParser,
+    pub field: RoundsField,
+}
+
+impl<'de> DeserializeSeed<'de> for RoundsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.parser.start(self.field)?;
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RoundsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of rounds")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<u64>()? {
+            self.parser.push(value);
+        }
+        Ok(())
+    }
+}
+
+/// Sums an array without materializing it, e.g. the operations of every round of a
+/// `b.RunParallel` benchmark.
+pub struct SumSeed;
+
+impl<'de> DeserializeSeed<'de> for SumSeed {
+    type Value = u64;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SumSeed {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of integers")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        let mut sum = 0u64;
+        while let Some(value) = seq.next_element::<u64>()? {
+            sum = sum.saturating_add(value);
+        }
+        Ok(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn parse(arrays: &[(RoundsField, &str)]) -> serde_json::Result<RoundStats> {
+        let mut parser = RoundsParser::default();
+        for (field, json) in arrays {
+            let mut deserializer = serde_json::Deserializer::from_str(json);
+            RoundsSeed {
+                parser: &mut parser,
+                field: *field,
+            }
+            .deserialize(&mut deserializer)?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn test_rounds_are_paired_in_any_order() {
+        let times = (RoundsField::Times, "[1000, 2000, 6000, 8000]");
+        let iters = (RoundsField::Iters, "[1, 2, 2, 4]");
+        let expected = RoundStats::from_rounds(&[1, 2, 2, 4], &[1000, 2000, 6000, 8000]).finish();
+
+        for arrays in [[times, iters], [iters, times]] {
+            let stats = parse(&arrays).unwrap().finish();
+            assert_eq!(stats.rounds, 4);
+            assert_eq!(stats.iter_per_round, expected.iter_per_round);
+            assert_eq!(stats.mean_ns, expected.mean_ns);
+            assert_eq!(stats.median_ns, expected.median_ns);
+        }
+    }
+
+    #[test]
+    fn test_constant_iterations_are_buffered_as_a_single_run() {
+        let mut parser = RoundsParser::default();
+        let iters = format!("[{}]", vec!["4"; 1000].join(","));
+        let mut deserializer = serde_json::Deserializer::from_str(&iters);
+        RoundsSeed {
+            parser: &mut parser,
+            field: RoundsField::Iters,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        assert_eq!(parser.buffered, [(4, 1000)]);
+    }
+
+    #[test]
+    fn test_times_first_are_buffered_until_the_iterations() {
+        let mut parser = RoundsParser::default();
+        let times = format!("[{}]", (0..1000).map(|i| (1000 + i).to_string()).join(","));
+        let mut deserializer = serde_json::Deserializer::from_str(&times);
+        RoundsSeed {
+            parser: &mut parser,
+            field: RoundsField::Times,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        // A run for every round
+        assert_eq!(parser.buffered.len(), 1000);
+
+        let iters = format!("[{}]", vec!["1"; 1000].join(","));
+        let mut deserializer = serde_json::Deserializer::from_str(&iters);
+        RoundsSeed {
+            parser: &mut parser,
+            field: RoundsField::Iters,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        assert!(parser.buffered.is_empty());
+        assert_eq!(
+            parser
+                .finish::<serde_json::Error>()
+                .unwrap()
+                .finish()
+                .rounds,
+            1000
+        );
+    }
+
+    #[test]
+    fn test_rounds_without_counterpart_are_ignored() {
+        let stats = parse(&[
+            (RoundsField::Times, "[1000, 2000, 3000]"),
+            (RoundsField::Iters, "[1, 1]"),
+        ])
+        .unwrap();
+        assert_eq!(stats.finish().rounds, 2);
+
+        let stats = parse(&[
+            (RoundsField::Iters, "[1]"),
+            (RoundsField::Times, "[1000, 2000]"),
+        ])
+        .unwrap();
+        assert_eq!(stats.finish().rounds, 1);
+    }
+
+    #[test]
+    fn test_missing_or_duplicate_rounds() {
+        let error = parse(&[(RoundsField::Times, "[]")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "missing field `codspeed_iters_per_round`"
+        );
+
+        let error = parse(&[(RoundsField::Iters, "[]")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "missing field `codspeed_time_per_round_ns`"
+        );
+
+        let error = parse(&[(RoundsField::Times, "[]"), (RoundsField::Times, "[]")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "duplicate field `codspeed_time_per_round_ns`"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::hooks::{self, Environment, Integration};
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Metadata of the whole run, written to `<profile dir>/metadata.json`.
+///
+/// It's written next to the `results` folder rather than inside of it, since every file of that
+/// folder is parsed as walltime results.
+///
+/// The environment is only available with `--local-hooks`: otherwise, the benchmark processes send
+/// it to the CodSpeed runner through the instrument hooks, and `environment` and `pids` are empty.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub runner: Integration,
+    /// Environment of all the benchmark processes of the run (Go version, build settings, module
+    /// dependencies, ...), see [`merge_environments`].
+    pub environment: Environment,
+    /// Benchmark processes of the run which reported their environment.
+    pub pids: Vec<u32>,
+}
+
+impl RunMetadata {
+    /// Collects the environments written by the overlay of every benchmark process, which it only
+    /// does with `--local-hooks`.
+    pub fn collect(profile_dir: &Path) -> Self {
+        let environments = hooks::load_environments(profile_dir);
+        RunMetadata {
+            runner: Integration {
+                name: "codspeed-go".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            pids: environments.keys().copied().collect(),
+            environment: merge_environments(environments.into_values()),
+        }
+    }
+
+    pub fn path(profile_dir: &Path) -> PathBuf {
+        profile_dir.join("metadata.json")
+    }
+
+    pub fn write(&self, profile_dir: &Path) -> Result<()> {
+        let path = Self::path(profile_dir);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write run metadata to {path:?}"))?;
+        info!("Run metadata written to {path:?}");
+        Ok(())
+    }
+}
+
+/// Merges the environments of the benchmark processes. Each package is built into its own test
+/// binary, so the values can differ (e.g. the dependencies of each package). Values that differ
+/// are all kept, sorted and separated by `, `.
+fn merge_environments(environments: impl IntoIterator<Item = Environment>) -> Environment {
+    let mut merged: BTreeMap<String, BTreeMap<String, BTreeSet<String>>> = BTreeMap::new();
+    for environment in environments {
+        for (section_name, section) in environment {
+            let merged_section = merged.entry(section_name).or_default();
+            for (key, value) in section {
+                merged_section.entry(key).or_default().insert(value);
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(section_name, section)| {
+            let section = section
+                .into_iter()
+                .map(|(key, values)| {
+                    if values.len() > 1 {
+                        debug!(
+                            "Environment {section_name}.{key} differs between processes: {values:?}"
+                        );
+                    }
+                    (key, values.into_iter().collect::<Vec<_>>().join(", "))
+                })
+                .collect();
+            (section_name, section)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn environment(sections: &[(&str, &str, &str)]) -> Environment {
+        let mut environment = Environment::new();
+        for (section, key, value) in sections {
+            environment
+                .entry(section.to_string())
+                .or_default()
+                .insert(key.to_string(), value.to_string());
+        }
+        environment
+    }
+
+    #[test]
+    fn test_merge_environments() {
+        let merged = merge_environments([
+            environment(&[
+                ("go", "version", "go1.25.0"),
+                ("go.deps", "github.com/a/a", "v1.0.0"),
+            ]),
+            environment(&[
+                ("go", "version", "go1.25.0"),
+                ("go.build", "CGO_ENABLED", "1"),
+                ("go.deps", "github.com/a/a", "v1.1.0"),
+            ]),
+        ]);
+
+        assert_eq!(
+            merged,
+            environment(&[
+                ("go", "version", "go1.25.0"),
+                ("go.build", "CGO_ENABLED", "1"),
+                ("go.deps", "github.com/a/a", "v1.0.0, v1.1.0"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_collect_and_write() {
+        let profile_dir = TempDir::new().unwrap();
+        let environment_dir = profile_dir.path().join("environment");
+        std::fs::create_dir(&environment_dir).unwrap();
+        std::fs::write(
+            environment_dir.join("1.json"),
+            r#"{"go": {"version": "go1.25.0", "goos": "linux"}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            environment_dir.join("2.json"),
+            r#"{"go": {"version": "go1.25.0", "goos": "linux"}}"#,
+        )
+        .unwrap();
+        std::fs::write(environment_dir.join("README.md"), "").unwrap();
+
+        let metadata = RunMetadata::collect(profile_dir.path());
+        assert_eq!(metadata.pids, vec![1, 2]);
+        assert_eq!(metadata.environment["go"]["goos"], "linux");
+
+        metadata.write(profile_dir.path()).unwrap();
+        let written: RunMetadata = serde_json::from_str(
+            &std::fs::read_to_string(RunMetadata::path(profile_dir.path())).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(written.environment, metadata.environment);
+    }
+
+    #[test]
+    fn test_collect_without_environment() {
+        let profile_dir = TempDir::new().unwrap();
+        let metadata = RunMetadata::collect(profile_dir.path());
+        assert!(metadata.pids.is_empty());
+        assert!(metadata.environment.is_empty());
+    }
+}
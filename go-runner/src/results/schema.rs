@@ -0,0 +1,677 @@
+//! Schema of the walltime results, written by the go-runner to `<profile dir>/results/<pid>.json`.
+//!
+//! The types can be used to parse the results in other tools:
+//! ```no_run
+//! use codspeed_go_runner::results::schema::WalltimeResults;
+//!
+//! let file = std::fs::File::open("/tmp/codspeed/results/1234.json")?;
+//! let results: WalltimeResults = serde_json::from_reader(file)?;
+//! for benchmark in &results.benchmarks {
+//!     println!("{}: {:.2}ns", benchmark.metadata.uri, benchmark.stats().median_ns());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The computed fields (stats, config, ...) can only be read through accessors, since they're
+//! derived from the measurements by the go-runner. The JSON Schema of the results is available
+//! with [`json_schema`], and committed to `schema/walltime-results.schema.json`.
+
+use crate::hooks::InstrumentHooksData;
+use crate::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of the results schema, bumped on every breaking change (removed or renamed fields,
+/// changed semantics). Adding optional fields isn't a breaking change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Results written before [`SCHEMA_VERSION`] was introduced are parsed as version 1.
+fn unversioned() -> u32 {
+    1
+}
+
+/// Returns the JSON Schema of [`WalltimeResults`].
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(WalltimeResults)
+}
+
+/// Identifies a benchmark.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BenchmarkMetadata {
+    /// Name of the benchmark, with the sub-benchmarks separated by `::`.
+    pub name: String,
+    /// `<file path>::<name>`, where the path is relative to the URI root (the git root by
+    /// default).
+    pub uri: String,
+
+    /// Line of the benchmark function in the benchmark file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Import path of the package containing the benchmark.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Fully qualified name of the benchmark function, which tells apart benchmarks of the
+    /// internal and external (`_test`) test packages, e.g. `example.com/pkg_test.BenchmarkFoo`.
+    /// Sub-benchmarks point to the function passed to `b.Run`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+
+    /// Parameters parsed from the `key=value` segments of the benchmark name, e.g.
+    /// `BenchmarkCodec::size=1024::codec=json`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+
+    /// Only available for `b.RunParallel` benchmarks, whose time per iteration is the wall time of
+    /// all the goroutines divided by the number of operations, not the latency of an operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<Parallelism>,
+}
+
+/// Goroutines started by `b.RunParallel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Parallelism {
+    /// `parallelism * gomaxprocs`
+    pub goroutines: u64,
+    /// Set with `b.SetParallelism`, defaults to 1.
+    pub parallelism: u64,
+    pub gomaxprocs: u64,
+    /// Operations executed by all the goroutines, over all the rounds.
+    #[serde(default)]
+    pub total_ops: u64,
+}
+
+/// Statistics of the time per iteration over the measured rounds. Each round is weighted by its
+/// number of iterations.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BenchmarkStats {
+    pub(crate) min_ns: f64,
+    pub(crate) max_ns: f64,
+    pub(crate) mean_ns: f64,
+    pub(crate) stdev_ns: f64,
+
+    pub(crate) q1_ns: f64,
+    pub(crate) median_ns: f64,
+    pub(crate) q3_ns: f64,
+
+    pub(crate) rounds: u64,
+    /// Total time of the rounds, in seconds.
+    pub(crate) total_time: f64,
+    /// Coefficient of variation, `stdev / mean`.
+    #[serde(default)]
+    pub(crate) cv: f64,
+    /// Median absolute deviation from the median (not scaled to estimate the standard deviation).
+    #[serde(default)]
+    pub(crate) mad_ns: f64,
+    /// Change of the time per iteration over the rounds (in execution order), relative to the
+    /// mean, from a least squares fit: e.g. `0.1` when the benchmark got 10% slower during the
+    /// run.
+    #[serde(default)]
+    pub(crate) drift: f64,
+    /// Sarle's bimodality coefficient of the rounds (without the IQR outliers). Values above 5/9
+    /// hint at a bimodal distribution, see [`crate::results::stability`].
+    #[serde(default)]
+    pub(crate) bimodality_coefficient: f64,
+    /// Bootstrap confidence intervals of the mean and median. Only available with at least 2
+    /// rounds, unless disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mean_ci: Option<ConfidenceInterval>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) median_ci: Option<ConfidenceInterval>,
+
+    /// Rounds outside of `[q1 - 1.5 * iqr, q3 + 1.5 * iqr]`.
+    pub(crate) iqr_outlier_rounds: u64,
+    /// Rounds outside of `[mean - 3 * stdev, mean + 3 * stdev]`.
+    pub(crate) stdev_outlier_rounds: u64,
+    /// Mean number of iterations per round, rounded. Kept for backward compatibility, see the
+    /// `iter_per_round_*` fields for the actual distribution.
+    pub(crate) iter_per_round: u64,
+    #[serde(default)]
+    pub(crate) iter_per_round_min: u64,
+    #[serde(default)]
+    pub(crate) iter_per_round_max: u64,
+    #[serde(default)]
+    pub(crate) iter_per_round_mean: f64,
+    /// Iterations of the warmup phase, which aren't part of the rounds.
+    pub(crate) warmup_iters: u64,
+
+    /// Only available when the benchmark called `b.SetBytes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bytes_per_op: Option<u64>,
+    /// Throughput in MB/s (10^6 bytes per second), like `go test` reports it. Computed from the
+    /// mean and median time per iteration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mean_mb_per_s: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) median_mb_per_s: Option<f64>,
+
+    /// Whether the quantiles (and thus the IQR outliers) are estimated, which is the case for
+    /// benchmarks with a very large number of rounds. They're then within 0.1% of the exact ones.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) approximate_quantiles: bool,
+
+    /// Stats of the rounds left by the outlier policy (see [`BenchmarkConfig::outlier_policy`]),
+    /// while the other stats are computed from all the rounds. Only available when a policy is
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) filtered: Option<Box<BenchmarkStats>>,
+}
+
+impl BenchmarkStats {
+    pub fn min_ns(&self) -> f64 {
+        self.min_ns
+    }
+
+    pub fn max_ns(&self) -> f64 {
+        self.max_ns
+    }
+
+    pub fn mean_ns(&self) -> f64 {
+        self.mean_ns
+    }
+
+    pub fn stdev_ns(&self) -> f64 {
+        self.stdev_ns
+    }
+
+    pub fn q1_ns(&self) -> f64 {
+        self.q1_ns
+    }
+
+    pub fn median_ns(&self) -> f64 {
+        self.median_ns
+    }
+
+    pub fn q3_ns(&self) -> f64 {
+        self.q3_ns
+    }
+
+    pub fn rounds(&self) -> u64 {
+        self.rounds
+    }
+
+    /// Total time of the rounds, in seconds.
+    pub fn total_time(&self) -> f64 {
+        self.total_time
+    }
+
+    pub fn iqr_outlier_rounds(&self) -> u64 {
+        self.iqr_outlier_rounds
+    }
+
+    pub fn stdev_outlier_rounds(&self) -> u64 {
+        self.stdev_outlier_rounds
+    }
+
+    /// Mean number of iterations per round, rounded.
+    pub fn iter_per_round(&self) -> u64 {
+        self.iter_per_round
+    }
+
+    pub fn iter_per_round_min(&self) -> u64 {
+        self.iter_per_round_min
+    }
+
+    pub fn iter_per_round_max(&self) -> u64 {
+        self.iter_per_round_max
+    }
+
+    pub fn iter_per_round_mean(&self) -> f64 {
+        self.iter_per_round_mean
+    }
+
+    pub fn warmup_iters(&self) -> u64 {
+        self.warmup_iters
+    }
+
+    pub fn bytes_per_op(&self) -> Option<u64> {
+        self.bytes_per_op
+    }
+
+    pub fn mean_mb_per_s(&self) -> Option<f64> {
+        self.mean_mb_per_s
+    }
+
+    pub fn median_mb_per_s(&self) -> Option<f64> {
+        self.median_mb_per_s
+    }
+
+    pub fn approximate_quantiles(&self) -> bool {
+        self.approximate_quantiles
+    }
+
+    pub fn cv(&self) -> f64 {
+        self.cv
+    }
+
+    pub fn mad_ns(&self) -> f64 {
+        self.mad_ns
+    }
+
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    pub fn bimodality_coefficient(&self) -> f64 {
+        self.bimodality_coefficient
+    }
+
+    pub fn mean_ci(&self) -> Option<&ConfidenceInterval> {
+        self.mean_ci.as_ref()
+    }
+
+    pub fn median_ci(&self) -> Option<&ConfidenceInterval> {
+        self.median_ci.as_ref()
+    }
+
+    pub fn filtered(&self) -> Option<&BenchmarkStats> {
+        self.filtered.as_deref()
+    }
+}
+
+/// Confidence interval of a statistic, from the percentiles of its bootstrap distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConfidenceInterval {
+    /// e.g. `0.95`
+    pub(crate) level: f64,
+    pub(crate) low_ns: f64,
+    pub(crate) high_ns: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    pub fn low_ns(&self) -> f64 {
+        self.low_ns
+    }
+
+    pub fn high_ns(&self) -> f64 {
+        self.high_ns
+    }
+}
+
+/// Effective round strategy of a benchmark. The values are only available for benchmarks that
+/// are split into rounds by the overlay (i.e. not for `b.Loop()` benchmarks or a fixed iteration
+/// count), except for `max_time_ns`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+pub struct BenchmarkConfig {
+    pub(crate) warmup_time_ns: Option<f64>,
+    /// Part of the format shared with the other CodSpeed integrations, never set since the
+    /// overlay doesn't enforce a minimum round time.
+    pub(crate) min_round_time_ns: Option<f64>,
+    /// Time of a round at the speed estimated by the warmup, which the rounds don't have to match.
+    pub(crate) target_round_time_ns: Option<f64>,
+    pub(crate) max_time_ns: Option<f64>,
+    pub(crate) max_rounds: Option<u64>,
+    /// How the outlier rounds are handled in [`BenchmarkStats::filtered`].
+    #[serde(default)]
+    pub(crate) outlier_policy: OutlierPolicy,
+}
+
+/// How the outlier rounds of a benchmark are handled in its filtered stats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierPolicy {
+    /// No filtered stats.
+    #[default]
+    None,
+    /// Rounds outside of `[q1 - 1.5 * iqr, q3 + 1.5 * iqr]` are removed.
+    Iqr,
+    /// Rounds further than 3 scaled MADs (`1.4826 * mad`, which estimates the standard deviation)
+    /// from the median are removed. Nothing is removed when the MAD is 0.
+    Mad,
+    /// Rounds below the 5th percentile or above the 95th percentile are clamped to them.
+    Winsorize,
+}
+
+impl OutlierPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutlierPolicy::None => "none",
+            OutlierPolicy::Iqr => "iqr",
+            OutlierPolicy::Mad => "mad",
+            OutlierPolicy::Winsorize => "winsorize",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(OutlierPolicy::None),
+            "iqr" => Some(OutlierPolicy::Iqr),
+            "mad" => Some(OutlierPolicy::Mad),
+            "winsorize" => Some(OutlierPolicy::Winsorize),
+            _ => None,
+        }
+    }
+}
+
+impl BenchmarkConfig {
+    pub fn warmup_time_ns(&self) -> Option<f64> {
+        self.warmup_time_ns
+    }
+
+    pub fn min_round_time_ns(&self) -> Option<f64> {
+        self.min_round_time_ns
+    }
+
+    pub fn target_round_time_ns(&self) -> Option<f64> {
+        self.target_round_time_ns
+    }
+
+    pub fn max_time_ns(&self) -> Option<f64> {
+        self.max_time_ns
+    }
+
+    pub fn max_rounds(&self) -> Option<u64> {
+        self.max_rounds
+    }
+
+    pub fn outlier_policy(&self) -> OutlierPolicy {
+        self.outlier_policy
+    }
+}
+
+/// How the value of a custom metric relates to the benchmark iterations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricAggregation {
+    /// Average per iteration, for units ending with `/op` (e.g. `allocs/op`).
+    PerOp,
+    /// Computed over the whole benchmark run (e.g. `msgs/s`).
+    PerBenchmark,
+}
+
+/// Metric reported with `b.ReportMetric`. Go only keeps the last reported value for each unit,
+/// so there is a single value per benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CustomMetric {
+    pub value: f64,
+    pub unit: String,
+    pub aggregation: MetricAggregation,
+}
+
+impl CustomMetric {
+    pub fn new(unit: String, value: f64) -> Self {
+        let aggregation = if unit.ends_with("/op") {
+            MetricAggregation::PerOp
+        } else {
+            MetricAggregation::PerBenchmark
+        };
+        CustomMetric {
+            value,
+            unit,
+            aggregation,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkStatus {
+    #[default]
+    Ok,
+    Failed,
+    Skipped,
+}
+
+/// Benchmark that failed or was skipped, which thus has no measurements.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UnmeasuredBenchmark {
+    #[serde(flatten)]
+    pub metadata: BenchmarkMetadata,
+
+    pub status: BenchmarkStatus,
+    /// Output of the benchmark, which contains the failure or skip reason.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Describes why a benchmark executed fewer rounds than planned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Truncation {
+    /// e.g. `max_time_exceeded` when the benchmark ran longer than `benchtime * max_time_multiplier`
+    pub reason: String,
+    pub planned_rounds: u64,
+    pub actual_rounds: u64,
+}
+
+/// Measured benchmark.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalltimeBenchmark {
+    #[serde(flatten)]
+    pub metadata: BenchmarkMetadata,
+
+    pub(crate) config: BenchmarkConfig,
+    pub(crate) stats: BenchmarkStats,
+
+    /// Custom metrics, by unit.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_metrics: BTreeMap<String, CustomMetric>,
+
+    /// Whether the benchmark was stopped before executing all the planned rounds, see `truncation`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<Truncation>,
+}
+
+impl WalltimeBenchmark {
+    pub fn config(&self) -> &BenchmarkConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &BenchmarkStats {
+        &self.stats
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Instrument {
+    /// Always `walltime`.
+    #[serde(rename = "type")]
+    pub(crate) type_: String,
+}
+
+/// Process that executed the benchmarks.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+    pub pid: u32,
+}
+
+/// Results of a benchmark process, i.e. the test binary of a package.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalltimeResults {
+    /// Version of the results schema, bumped on breaking changes. Results written before the
+    /// version was introduced are version 1.
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+
+    pub(crate) creator: Creator,
+    pub(crate) instrument: Instrument,
+    pub benchmarks: Vec<WalltimeBenchmark>,
+
+    /// Benchmarks that failed or were skipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmeasured_benchmarks: Vec<UnmeasuredBenchmark>,
+
+    /// Only available when the instrument hooks events are received by the go-runner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument_hooks: Option<InstrumentHooksData>,
+}
+
+impl WalltimeResults {
+    pub fn new(benchmarks: Vec<WalltimeBenchmark>, creator: Creator) -> Result<Self> {
+        Ok(WalltimeResults {
+            schema_version: SCHEMA_VERSION,
+            instrument: Instrument {
+                type_: "walltime".to_string(),
+            },
+            creator,
+            benchmarks,
+            unmeasured_benchmarks: Vec::new(),
+            instrument_hooks: None,
+        })
+    }
+
+    pub fn creator(&self) -> &Creator {
+        &self.creator
+    }
+
+    pub fn instrument_type(&self) -> &str {
+        &self.instrument.type_
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::{Integration, protocol::MarkerType};
+    use std::path::PathBuf;
+
+    fn schema_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schema/walltime-results.schema.json")
+    }
+
+    /// Results with all the optional fields set.
+    fn full_results() -> WalltimeResults {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            "BenchmarkCodec::size=1024".into(),
+            "codec_test.go::BenchmarkCodec::size=1024".into(),
+            &[10, 20],
+            &[1000, 3000],
+            1024,
+            None,
+        )
+        .with_warmup(5, 500)
+        .with_source(
+            Some(12),
+            Some("example.com/codec".into()),
+            Some("example.com/codec_test.BenchmarkCodec.func1".into()),
+        )
+        .with_truncation(Some(Truncation {
+            reason: "max_time_exceeded".into(),
+            planned_rounds: 100,
+            actual_rounds: 2,
+        }))
+        .with_parallelism(
+            Some(Parallelism {
+                goroutines: 8,
+                parallelism: 1,
+                gomaxprocs: 8,
+                total_ops: 0,
+            }),
+            30,
+        )
+        .with_custom_metrics(BTreeMap::from([("allocs/op".to_string(), 2.0)]));
+
+        let mut results = WalltimeResults::new(
+            vec![benchmark],
+            Creator {
+                name: "codspeed-go".into(),
+                version: "1.2.0".into(),
+                pid: 42,
+            },
+        )
+        .unwrap();
+        results.unmeasured_benchmarks.push(UnmeasuredBenchmark {
+            metadata: BenchmarkMetadata::new(
+                "BenchmarkBroken".into(),
+                "broken_test.go::BenchmarkBroken".into(),
+            ),
+            status: BenchmarkStatus::Failed,
+            message: Some("broken_test.go:3: failed".into()),
+        });
+        results.instrument_hooks = Some(InstrumentHooksData {
+            integration: Some(Integration {
+                name: "codspeed-go".into(),
+                version: "1.2.0".into(),
+            }),
+            environment: BTreeMap::from([(
+                "go".to_string(),
+                BTreeMap::from([("version".to_string(), "go1.25.0".to_string())]),
+            )]),
+            executed_benchmarks: vec!["codec_test.go::BenchmarkCodec::size=1024".into()],
+            markers: vec![MarkerType::BenchmarkStart(1), MarkerType::BenchmarkEnd(2)],
+        });
+        results
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = serde_json::to_value(full_results()).unwrap();
+        let parsed: WalltimeResults = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+
+        assert_eq!(parsed.schema_version, SCHEMA_VERSION);
+        assert_eq!(parsed.creator().pid, 42);
+        assert_eq!(parsed.instrument_type(), "walltime");
+
+        let benchmark = &parsed.benchmarks[0];
+        assert_eq!(benchmark.metadata.params["size"], "1024");
+        assert_eq!(benchmark.stats().rounds(), 2);
+        assert_eq!(benchmark.stats().mean_ns(), 4000. / 30.);
+        assert_eq!(benchmark.stats().warmup_iters(), 5);
+        assert_eq!(benchmark.stats().bytes_per_op(), Some(1024));
+        assert_eq!(benchmark.config().warmup_time_ns(), Some(500.));
+        assert_eq!(benchmark.config().max_rounds(), None);
+    }
+
+    #[test]
+    fn test_parse_unversioned_results() {
+        let json = r#"{
+            "creator": {"name": "codspeed-go", "version": "1.0.0", "pid": 1},
+            "instrument": {"type": "walltime"},
+            "benchmarks": [{
+                "name": "BenchmarkFib",
+                "uri": "fib_test.go::BenchmarkFib",
+                "config": {"warmup_time_ns": null, "min_round_time_ns": null, "max_time_ns": null, "max_rounds": null},
+                "stats": {
+                    "min_ns": 1.0, "max_ns": 1.0, "mean_ns": 1.0, "stdev_ns": 0.0,
+                    "q1_ns": 1.0, "median_ns": 1.0, "q3_ns": 1.0,
+                    "rounds": 1, "total_time": 1.0, "iqr_outlier_rounds": 0,
+                    "stdev_outlier_rounds": 0, "iter_per_round": 1, "warmup_iters": 0
+                }
+            }]
+        }"#;
+        let results: WalltimeResults = serde_json::from_str(json).unwrap();
+        assert_eq!(results.schema_version, 1);
+        assert_eq!(results.benchmarks[0].stats().median_ns(), 1.0);
+        assert!(results.unmeasured_benchmarks.is_empty());
+    }
+
+    /// The exported schema must be up to date, run with `UPDATE_SCHEMA=1` to regenerate it.
+    #[test]
+    fn test_json_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::create_dir_all(schema_path().parent().unwrap()).unwrap();
+            std::fs::write(schema_path(), &schema).unwrap();
+        }
+
+        let committed = std::fs::read_to_string(schema_path()).unwrap_or_default();
+        assert!(
+            committed == schema,
+            "{:?} is outdated, run the tests with UPDATE_SCHEMA=1 to regenerate it",
+            schema_path()
+        );
+    }
+
+    #[test]
+    fn test_json_schema_matches_results() {
+        let schema = serde_json::to_value(json_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+
+        let results = serde_json::to_value(full_results()).unwrap();
+        for key in results.as_object().unwrap().keys() {
+            assert!(properties.contains_key(key), "{key} is missing");
+        }
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["creator", "instrument", "benchmarks"])
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Stability report of the benchmarks, logged after the run and written to
+//! `<profile dir>/stability.json`.
+//!
+//! Every benchmark is graded from the stats of its rounds: the coefficient of variation, the
+//! drift across the rounds, the bimodality coefficient and the rate of IQR outliers. Each of them
+//! has a threshold above which the benchmark is noisy, and (except for the bimodality) a higher
+//! one above which it's unstable, since its results can't be trusted.
+
+use crate::prelude::*;
+use crate::results::walltime_results::WalltimeBenchmark;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Noisy and unstable thresholds of the coefficient of variation.
+const CV_THRESHOLDS: (f64, f64) = (0.05, 0.2);
+/// Noisy and unstable thresholds of the absolute drift.
+const DRIFT_THRESHOLDS: (f64, f64) = (0.05, 0.2);
+/// Noisy and unstable thresholds of the rate of IQR outlier rounds.
+const OUTLIER_RATE_THRESHOLDS: (f64, f64) = (0.05, 0.15);
+/// Bimodality coefficient above which the rounds are likely bimodal. Sarle's usual threshold is
+/// 5/9 (the coefficient of a uniform distribution), but right-skewed unimodal rounds, e.g. with an
+/// exponential tail of slow rounds, have a coefficient between 0.55 and 0.68, while two balanced
+/// modes are around 0.8 and above.
+const BIMODALITY_THRESHOLD: f64 = 0.7;
+/// Minimum number of rounds for the bimodality coefficient to be checked, since it's too noisy
+/// with fewer rounds.
+const BIMODALITY_MIN_ROUNDS: u64 = 50;
+
+/// Number of benchmarks listed in [`StabilityReport::worst_offenders`].
+const WORST_OFFENDERS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StabilityGrade {
+    Stable,
+    Noisy,
+    /// Too noisy for its results to be trusted.
+    Unstable,
+}
+
+impl StabilityGrade {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StabilityGrade::Stable => "stable",
+            StabilityGrade::Noisy => "noisy",
+            StabilityGrade::Unstable => "unstable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StabilityIssue {
+    /// High coefficient of variation.
+    HighVariation,
+    /// The rounds got slower or faster during the run.
+    Drift,
+    /// The rounds are split between two (or more) modes.
+    Bimodal,
+    /// Many rounds are IQR outliers.
+    Outliers,
+}
+
+/// Stability of a benchmark, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkStability {
+    pub uri: String,
+    pub pid: u32,
+    pub grade: StabilityGrade,
+    pub issues: Vec<StabilityIssue>,
+
+    pub rounds: u64,
+    pub cv: f64,
+    pub drift: f64,
+    pub bimodality_coefficient: f64,
+    pub outlier_rate: f64,
+    /// Highest ratio of a metric to its noisy threshold, which ranks the benchmarks of the same
+    /// grade.
+    pub score: f64,
+}
+
+impl BenchmarkStability {
+    pub fn new(pid: u32, benchmark: &WalltimeBenchmark) -> Self {
+        let stats = benchmark.stats();
+        let outlier_rate = if stats.rounds() == 0 {
+            0.0
+        } else {
+            stats.iqr_outlier_rounds() as f64 / stats.rounds() as f64
+        };
+
+        let mut stability = BenchmarkStability {
+            uri: benchmark.metadata.uri.clone(),
+            pid,
+            grade: StabilityGrade::Stable,
+            issues: Vec::new(),
+            rounds: stats.rounds(),
+            cv: stats.cv(),
+            drift: stats.drift(),
+            bimodality_coefficient: stats.bimodality_coefficient(),
+            outlier_rate,
+            score: 0.0,
+        };
+        stability.check(StabilityIssue::HighVariation, stats.cv(), CV_THRESHOLDS);
+        stability.check(StabilityIssue::Drift, stats.drift().abs(), DRIFT_THRESHOLDS);
+        if stats.rounds() >= BIMODALITY_MIN_ROUNDS {
+            stability.check(
+                StabilityIssue::Bimodal,
+                stats.bimodality_coefficient(),
+                (BIMODALITY_THRESHOLD, f64::INFINITY),
+            );
+        }
+        stability.check(
+            StabilityIssue::Outliers,
+            outlier_rate,
+            OUTLIER_RATE_THRESHOLDS,
+        );
+        stability
+    }
+
+    fn check(&mut self, issue: StabilityIssue, value: f64, (noisy, unstable): (f64, f64)) {
+        self.score = self.score.max(value / noisy);
+        let grade = if value >= unstable {
+            StabilityGrade::Unstable
+        } else if value >= noisy {
+            StabilityGrade::Noisy
+        } else {
+            return;
+        };
+        self.grade = self.grade.max(grade);
+        self.issues.push(issue);
+    }
+
+    fn describe_issues(&self) -> String {
+        self.issues
+            .iter()
+            .map(|issue| match issue {
+                StabilityIssue::HighVariation => format!("CV {:.1}%", self.cv * 100.0),
+                StabilityIssue::Drift => format!("drift {:+.1}%", self.drift * 100.0),
+                StabilityIssue::Bimodal => {
+                    format!("bimodal ({:.2})", self.bimodality_coefficient)
+                }
+                StabilityIssue::Outliers => {
+                    format!("{:.1}% outlier rounds", self.outlier_rate * 100.0)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Stability of all the benchmarks of a run.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StabilityReport {
+    pub stable: usize,
+    pub noisy: usize,
+    pub unstable: usize,
+    /// URIs of the least stable benchmarks, unstable ones first.
+    pub worst_offenders: Vec<String>,
+    /// Sorted from the least stable.
+    pub benchmarks: Vec<BenchmarkStability>,
+}
+
+impl StabilityReport {
+    pub fn new(mut benchmarks: Vec<BenchmarkStability>) -> Self {
+        benchmarks.sort_by(|a, b| {
+            b.grade
+                .cmp(&a.grade)
+                .then(b.score.total_cmp(&a.score))
+                .then_with(|| a.uri.cmp(&b.uri))
+        });
+
+        let count = |grade| {
+            benchmarks
+                .iter()
+                .filter(|benchmark| benchmark.grade == grade)
+                .count()
+        };
+        StabilityReport {
+            stable: count(StabilityGrade::Stable),
+            noisy: count(StabilityGrade::Noisy),
+            unstable: count(StabilityGrade::Unstable),
+            worst_offenders: benchmarks
+                .iter()
+                .filter(|benchmark| benchmark.grade != StabilityGrade::Stable)
+                .take(WORST_OFFENDERS)
+                .map(|benchmark| benchmark.uri.clone())
+                .collect(),
+            benchmarks,
+        }
+    }
+
+    pub fn path(profile_dir: &Path) -> PathBuf {
+        profile_dir.join("stability.json")
+    }
+
+    /// Writes the report next to the `results` folder, see [`crate::results::run_metadata`].
+    pub fn write(&self, profile_dir: &Path) -> Result<()> {
+        let path = Self::path(profile_dir);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write the stability report to {path:?}"))?;
+        info!("Stability report written to {path:?}");
+        Ok(())
+    }
+
+    pub fn log(&self) {
+        info!(
+            "Stability: {} stable, {} noisy, {} unstable",
+            self.stable, self.noisy, self.unstable
+        );
+        if self.worst_offenders.is_empty() {
+            return;
+        }
+
+        let worst_offenders: Vec<_> = self
+            .benchmarks
+            .iter()
+            .take(self.worst_offenders.len())
+            .map(|benchmark| {
+                format!(
+                    "{} ({}: {})",
+                    benchmark.uri,
+                    benchmark.grade.as_str(),
+                    benchmark.describe_issues()
+                )
+            })
+            .collect();
+        warn!(
+            "Least stable benchmarks, consider increasing -benchtime or reducing the noise of the \
+             machine:\n  - {}",
+            worst_offenders.join("\n  - ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stability(uri: &str, times_per_round_ns: &[u64]) -> BenchmarkStability {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            uri.into(),
+            uri.into(),
+            &vec![1; times_per_round_ns.len()],
+            times_per_round_ns,
+            0,
+            None,
+        );
+        BenchmarkStability::new(1, &benchmark)
+    }
+
+    #[test]
+    fn test_stable_benchmark() {
+        let times: Vec<u64> = (0..100).map(|i| 1000 + i % 10).collect();
+        let stability = stability("stable", &times);
+        assert_eq!(stability.grade, StabilityGrade::Stable);
+        assert!(stability.issues.is_empty());
+        assert!(stability.score < 1.0);
+    }
+
+    #[test]
+    fn test_drifting_benchmark() {
+        // 30% slower at the end of the run
+        let times: Vec<u64> = (0..100).map(|i| 1000 + 3 * i).collect();
+        let stability = stability("drift", &times);
+        assert_eq!(stability.grade, StabilityGrade::Unstable);
+        assert!(stability.issues.contains(&StabilityIssue::Drift));
+        assert!(stability.drift > 0.2, "{}", stability.drift);
+    }
+
+    #[test]
+    fn test_bimodal_benchmark() {
+        // Barely any variation, but half of the rounds are 10% slower
+        let times: Vec<u64> = (0..100).map(|i| 1000 + (i % 2) * 100).collect();
+        let stability = stability("bimodal", &times);
+        assert_eq!(stability.grade, StabilityGrade::Noisy);
+        assert_eq!(stability.issues, vec![StabilityIssue::Bimodal]);
+    }
+
+    #[test]
+    fn test_skewed_benchmark_is_not_bimodal() {
+        // Unimodal, with an exponential tail of slow rounds (shuffled to avoid a drift)
+        let times: Vec<u64> = (0..200)
+            .map(|i| {
+                let quantile = ((i * 37) % 200) as f64 / 200.0 + 0.0025;
+                1000 + (-100.0 * (1.0 - quantile).ln()) as u64
+            })
+            .collect();
+        let skewed = stability("skewed", &times);
+        assert!(
+            skewed.bimodality_coefficient > 5.0 / 9.0,
+            "{}",
+            skewed.bimodality_coefficient
+        );
+        assert!(!skewed.issues.contains(&StabilityIssue::Bimodal));
+
+        // Too few rounds for the bimodality coefficient to be checked
+        let times: Vec<u64> = (0..40).map(|i| 1000 + (i % 2) * 100).collect();
+        let few_rounds = stability("few_rounds", &times);
+        assert!(few_rounds.bimodality_coefficient > BIMODALITY_THRESHOLD);
+        assert!(few_rounds.issues.is_empty());
+    }
+
+    #[test]
+    fn test_benchmark_with_outliers() {
+        let times: Vec<u64> = (0..100)
+            .map(|i| if i % 5 == 0 { 5000 } else { 1000 + i % 10 })
+            .collect();
+        let stability = stability("outliers", &times);
+        assert_eq!(stability.grade, StabilityGrade::Unstable);
+        assert!(stability.issues.contains(&StabilityIssue::Outliers));
+        assert_eq!(stability.outlier_rate, 0.2);
+    }
+
+    #[test]
+    fn test_stability_report() {
+        let stable: Vec<u64> = (0..100).map(|i| 1000 + i % 10).collect();
+        let bimodal: Vec<u64> = (0..100).map(|i| 1000 + (i % 2) * 100).collect();
+        let drift: Vec<u64> = (0..100).map(|i| 1000 + 3 * i).collect();
+        let report = StabilityReport::new(vec![
+            stability("a_stable", &stable),
+            stability("b_bimodal", &bimodal),
+            stability("c_drift", &drift),
+        ]);
+
+        assert_eq!((report.stable, report.noisy, report.unstable), (1, 1, 1));
+        assert_eq!(report.worst_offenders, vec!["c_drift", "b_bimodal"]);
+        assert_eq!(report.benchmarks[2].uri, "a_stable");
+
+        let profile_dir = tempfile::TempDir::new().unwrap();
+        report.write(profile_dir.path()).unwrap();
+        let written = std::fs::read_to_string(StabilityReport::path(profile_dir.path())).unwrap();
+        let written: StabilityReport = serde_json::from_str(&written).unwrap();
+        assert_eq!(written.worst_offenders, report.worst_offenders);
+        assert_eq!(written.benchmarks[0].grade, StabilityGrade::Unstable);
+    }
+}
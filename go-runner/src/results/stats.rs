@@ -0,0 +1,1012 @@
+//! Statistics of the rounds of a benchmark, computed in a single pass with bounded memory.
+//!
+//! The mean, standard deviation and extrema are always exact. Quantiles and outliers are exact up
+//! to [`EXACT_QUANTILES_MAX_ROUNDS`] rounds; above that, the rounds are folded into a log-scale
+//! histogram whose quantiles are within [`SKETCH_RELATIVE_ACCURACY`] of the exact ones, so that
+//! benchmarks with millions of rounds don't need to keep (and sort) all of them.
+//!
+//! The confidence intervals of the mean and median are computed with a percentile bootstrap over
+//! the rounds, seeded from the [`StatsConfig`] so that the same rounds always give the same
+//! intervals.
+//!
+//! An [`OutlierPolicy`] can also be set, whose filtered stats are computed from the rounds left
+//! by the policy, in a second pass over the (sorted) rounds.
+
+use crate::config::StatsConfig;
+use crate::results::walltime_results::{BenchmarkStats, ConfidenceInterval, OutlierPolicy};
+
+/// Rounds kept as-is to compute the exact quantiles (16 bytes each).
+pub const EXACT_QUANTILES_MAX_ROUNDS: usize = 100_000;
+/// Maximum relative error of the quantiles of the histogram.
+pub const SKETCH_RELATIVE_ACCURACY: f64 = 0.001;
+
+/// Rounds drawn by every bootstrap resample. Benchmarks with more rounds use an m-out-of-n
+/// bootstrap, whose deviations are scaled by `sqrt(m / n)`, to bound the cost of the resamples.
+pub const BOOTSTRAP_MAX_DRAWS: u64 = 5_000;
+
+const IQR_OUTLIER_FACTOR: f64 = 1.5;
+const STDEV_OUTLIER_FACTOR: f64 = 3.0;
+const MAD_OUTLIER_FACTOR: f64 = 3.0;
+/// Scales the MAD to estimate the standard deviation of normally distributed rounds.
+const MAD_NORMAL_SCALE: f64 = 1.4826;
+/// Rounds below this quantile (or above `1 - WINSORIZE_QUANTILE`) are clamped by
+/// [`OutlierPolicy::Winsorize`].
+const WINSORIZE_QUANTILE: f64 = 0.05;
+
+/// Accumulates the rounds of a benchmark, see the [module documentation](self).
+#[derive(Debug)]
+pub struct RoundStats {
+    /// Number of rounds, including the ones without iterations.
+    pushed_rounds: u64,
+    total_time_ns: u128,
+    iters_per_round_min: u64,
+    iters_per_round_max: u64,
+    iters_per_round_sum: u128,
+
+    /// Rounds with iterations, which are the only ones carrying information about the time per
+    /// iteration.
+    rounds: u64,
+    total_iters: u64,
+    round_time_ns: u128,
+    min_ns: f64,
+    max_ns: f64,
+    /// Running weighted mean and sum of squared differences (West's algorithm), for the variance.
+    running_mean_ns: f64,
+    weighted_sq_diff: f64,
+    /// Running least squares fit of the time per iteration against the index of the round, for
+    /// the drift. Only the rounds pushed in execution order are part of it.
+    trend: Trend,
+
+    quantiles: Quantiles,
+    exact_max_rounds: usize,
+    config: StatsConfig,
+}
+
+#[derive(Debug)]
+enum Quantiles {
+    Exact(Vec<WeightedRound>),
+    Sketch(Sketch),
+}
+
+impl Default for RoundStats {
+    fn default() -> Self {
+        Self::with_exact_max_rounds(EXACT_QUANTILES_MAX_ROUNDS)
+    }
+}
+
+impl RoundStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Computes the exact quantiles up to `exact_max_rounds` rounds.
+    pub fn with_exact_max_rounds(exact_max_rounds: usize) -> Self {
+        RoundStats {
+            pushed_rounds: 0,
+            total_time_ns: 0,
+            iters_per_round_min: u64::MAX,
+            iters_per_round_max: 0,
+            iters_per_round_sum: 0,
+            rounds: 0,
+            total_iters: 0,
+            round_time_ns: 0,
+            min_ns: f64::INFINITY,
+            max_ns: f64::NEG_INFINITY,
+            running_mean_ns: 0.0,
+            weighted_sq_diff: 0.0,
+            trend: Trend::default(),
+            quantiles: Quantiles::Exact(Vec::new()),
+            exact_max_rounds,
+            config: StatsConfig::default(),
+        }
+    }
+
+    pub fn config(&self) -> &StatsConfig {
+        &self.config
+    }
+
+    /// Computes the statistics with the given settings, rather than the default ones.
+    pub fn with_config(mut self, config: StatsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn from_rounds(iters_per_round: &[u64], times_per_round_ns: &[u64]) -> Self {
+        let mut stats = Self::default();
+        for (&time_ns, &iters) in times_per_round_ns.iter().zip(iters_per_round) {
+            stats.push(time_ns, iters);
+        }
+        stats
+    }
+
+    /// Adds a round that executed `iters` iterations in `time_ns`.
+    pub fn push(&mut self, time_ns: u64, iters: u64) {
+        self.pushed_rounds += 1;
+        self.total_time_ns += time_ns as u128;
+        self.iters_per_round_min = self.iters_per_round_min.min(iters);
+        self.iters_per_round_max = self.iters_per_round_max.max(iters);
+        self.iters_per_round_sum += iters as u128;
+
+        // Every round is weighted by its number of iterations, so that the statistics match the
+        // ones computed over all the iterations. Rounds without iterations carry no information.
+        if iters == 0 {
+            return;
+        }
+        let round = WeightedRound {
+            time_per_iter_ns: time_ns as f64 / iters as f64,
+            iters,
+            rounds: 1,
+        };
+        self.trend.push(round.time_per_iter_ns);
+        self.add(round, time_ns as u128);
+    }
+
+    /// Adds rounds that were already weighted, e.g. the rounds kept by an outlier policy. Their
+    /// iterations are assumed to be evenly split between them.
+    fn push_weighted(&mut self, round: WeightedRound) {
+        let time_ns = round.time_per_iter_ns * round.iters as f64;
+        let iters_per_round = round.iters / round.rounds;
+        self.pushed_rounds += round.rounds;
+        self.total_time_ns += time_ns.round() as u128;
+        self.iters_per_round_min = self.iters_per_round_min.min(iters_per_round);
+        self.iters_per_round_max = self.iters_per_round_max.max(iters_per_round);
+        self.iters_per_round_sum += round.iters as u128;
+        self.add(round, time_ns.round() as u128);
+    }
+
+    fn add(&mut self, round: WeightedRound, time_ns: u128) {
+        let WeightedRound {
+            time_per_iter_ns,
+            iters,
+            rounds,
+        } = round;
+        self.rounds += rounds;
+        self.total_iters += iters;
+        self.round_time_ns += time_ns;
+        self.min_ns = self.min_ns.min(time_per_iter_ns);
+        self.max_ns = self.max_ns.max(time_per_iter_ns);
+
+        let delta = time_per_iter_ns - self.running_mean_ns;
+        self.running_mean_ns += delta * iters as f64 / self.total_iters as f64;
+        self.weighted_sq_diff += iters as f64 * delta * (time_per_iter_ns - self.running_mean_ns);
+
+        match &mut self.quantiles {
+            Quantiles::Exact(rounds) if rounds.len() < self.exact_max_rounds => rounds.push(round),
+            Quantiles::Exact(rounds) => {
+                let mut sketch = Sketch::new(SKETCH_RELATIVE_ACCURACY);
+                for round in rounds.drain(..).chain([round]) {
+                    sketch.insert(round);
+                }
+                self.quantiles = Quantiles::Sketch(sketch);
+            }
+            Quantiles::Sketch(sketch) => sketch.insert(round),
+        }
+    }
+
+    /// Whether the quantiles are estimated from the histogram rather than exact.
+    pub fn is_approximate(&self) -> bool {
+        matches!(self.quantiles, Quantiles::Sketch(_))
+    }
+
+    /// Computes the statistics of the rounds. The fields that don't depend on the rounds (warmup,
+    /// throughput) are left empty.
+    pub fn finish(self) -> BenchmarkStats {
+        let approximate_quantiles = self.is_approximate();
+        let (min_ns, max_ns) = if self.rounds == 0 {
+            (0.0, 0.0)
+        } else {
+            (self.min_ns, self.max_ns)
+        };
+
+        let rounds = self.rounds;
+        let total_iters = self.total_iters;
+        let mean_ns = if total_iters == 0 {
+            0.0
+        } else {
+            self.round_time_ns as f64 / total_iters as f64
+        };
+
+        let stdev_ns = if rounds < 2 {
+            0.0
+        } else {
+            // Weighted variance, with Bessel's correction based on the number of rounds since
+            // the iterations of a round aren't independent samples.
+            let variance = self.weighted_sq_diff.max(0.0) / total_iters as f64 * rounds as f64
+                / (rounds - 1) as f64;
+            variance.sqrt()
+        };
+
+        // Sorted rounds, or histogram buckets, by time per iteration
+        let sorted_rounds = match self.quantiles {
+            Quantiles::Exact(mut rounds) => {
+                rounds.sort_by(|a, b| a.time_per_iter_ns.total_cmp(&b.time_per_iter_ns));
+                rounds
+            }
+            Quantiles::Sketch(sketch) => sketch.into_rounds(min_ns, max_ns),
+        };
+
+        let q1_ns = weighted_quantile(&sorted_rounds, total_iters, 0.25);
+        let median_ns = weighted_quantile(&sorted_rounds, total_iters, 0.5);
+        let q3_ns = weighted_quantile(&sorted_rounds, total_iters, 0.75);
+
+        let cv = if mean_ns > 0.0 {
+            stdev_ns / mean_ns
+        } else {
+            0.0
+        };
+        let mut deviations: Vec<_> = sorted_rounds
+            .iter()
+            .map(|round| WeightedRound {
+                time_per_iter_ns: (round.time_per_iter_ns - median_ns).abs(),
+                ..*round
+            })
+            .collect();
+        deviations.sort_by(|a, b| a.time_per_iter_ns.total_cmp(&b.time_per_iter_ns));
+        let mad_ns = weighted_quantile(&deviations, total_iters, 0.5);
+        drop(deviations);
+
+        let bimodality_coefficient = bimodality_coefficient(
+            &sorted_rounds,
+            q1_ns - IQR_OUTLIER_FACTOR * (q3_ns - q1_ns),
+            q3_ns + IQR_OUTLIER_FACTOR * (q3_ns - q1_ns),
+        );
+
+        let (mean_ci, median_ci) =
+            bootstrap(&sorted_rounds, rounds, mean_ns, median_ns, &self.config).unzip();
+
+        let count_outside = |low: f64, high: f64| {
+            sorted_rounds
+                .iter()
+                .filter(|round| round.time_per_iter_ns < low || round.time_per_iter_ns > high)
+                .map(|round| round.rounds)
+                .sum::<u64>()
+        };
+        let iqr_ns = q3_ns - q1_ns;
+        let iqr_outlier_rounds = count_outside(
+            q1_ns - IQR_OUTLIER_FACTOR * iqr_ns,
+            q3_ns + IQR_OUTLIER_FACTOR * iqr_ns,
+        );
+        let stdev_outlier_rounds = count_outside(
+            mean_ns - STDEV_OUTLIER_FACTOR * stdev_ns,
+            mean_ns + STDEV_OUTLIER_FACTOR * stdev_ns,
+        );
+
+        let (iter_per_round_min, iter_per_round_mean) = if self.pushed_rounds == 0 {
+            (0, 0.0)
+        } else {
+            (
+                self.iters_per_round_min,
+                self.iters_per_round_sum as f64 / self.pushed_rounds as f64,
+            )
+        };
+
+        let mut stats = BenchmarkStats {
+            min_ns,
+            max_ns,
+            mean_ns,
+            stdev_ns,
+            q1_ns,
+            median_ns,
+            q3_ns,
+            cv,
+            mad_ns,
+            drift: self.trend.drift(),
+            bimodality_coefficient,
+            mean_ci,
+            median_ci,
+            rounds,
+            total_time: self.total_time_ns as f64 / 1_000_000_000.0,
+            iqr_outlier_rounds,
+            stdev_outlier_rounds,
+            iter_per_round: iter_per_round_mean.round() as u64,
+            iter_per_round_min,
+            iter_per_round_max: self.iters_per_round_max,
+            iter_per_round_mean,
+            warmup_iters: 0,
+            bytes_per_op: None,
+            mean_mb_per_s: None,
+            median_mb_per_s: None,
+            approximate_quantiles,
+            filtered: None,
+        };
+
+        let outlier_policy = self.config.outlier_policy;
+        if outlier_policy != OutlierPolicy::None && rounds > 0 {
+            let config = StatsConfig {
+                outlier_policy: OutlierPolicy::None,
+                ..self.config
+            };
+            let mut filtered =
+                RoundStats::with_exact_max_rounds(self.exact_max_rounds).with_config(config);
+            for round in filter_outliers(outlier_policy, &sorted_rounds, total_iters, &stats) {
+                filtered.push_weighted(round);
+            }
+            let mut filtered = filtered.finish();
+            // The rounds of the histogram are already approximate
+            filtered.approximate_quantiles |= approximate_quantiles;
+            // The filtered rounds aren't in execution order anymore
+            filtered.drift = stats.drift;
+            stats.filtered = Some(Box::new(filtered));
+        }
+        stats
+    }
+}
+
+/// Least squares fit of the time per iteration against the index of the round, updated like
+/// Welford's algorithm.
+#[derive(Debug, Default)]
+struct Trend {
+    rounds: u64,
+    mean_index: f64,
+    mean_ns: f64,
+    /// Sums of the products of the differences to the means.
+    co_moment: f64,
+    index_sq_diff: f64,
+}
+
+impl Trend {
+    fn push(&mut self, time_per_iter_ns: f64) {
+        let index = self.rounds as f64;
+        self.rounds += 1;
+        let delta_index = index - self.mean_index;
+        self.mean_index += delta_index / self.rounds as f64;
+        self.mean_ns += (time_per_iter_ns - self.mean_ns) / self.rounds as f64;
+        self.co_moment += delta_index * (time_per_iter_ns - self.mean_ns);
+        self.index_sq_diff += delta_index * (index - self.mean_index);
+    }
+
+    /// Change of the time per iteration from the first to the last round along the fitted line,
+    /// relative to the mean, e.g. `0.1` when the benchmark got 10% slower during the run.
+    fn drift(&self) -> f64 {
+        if self.rounds < 2 || self.index_sq_diff == 0.0 || self.mean_ns <= 0.0 {
+            return 0.0;
+        }
+        let slope = self.co_moment / self.index_sq_diff;
+        slope * (self.rounds - 1) as f64 / self.mean_ns
+    }
+}
+
+/// Sarle's bimodality coefficient of the rounds within `[low, high]` (the IQR fences, so that a
+/// few outliers don't make the rounds look bimodal), weighted by their iterations. Values above
+/// 5/9 (the coefficient of a uniform distribution) hint at a bimodal distribution.
+fn bimodality_coefficient(sorted_rounds: &[WeightedRound], low: f64, high: f64) -> f64 {
+    let within = || {
+        sorted_rounds
+            .iter()
+            .filter(move |round| (low..=high).contains(&round.time_per_iter_ns))
+    };
+    let (rounds, iters, time_ns) = within().fold((0, 0, 0.0), |(rounds, iters, time_ns), round| {
+        (
+            rounds + round.rounds,
+            iters + round.iters,
+            time_ns + round.time_per_iter_ns * round.iters as f64,
+        )
+    });
+    if rounds < 4 {
+        return 0.0;
+    }
+
+    let mean_ns = time_ns / iters as f64;
+    let moment = |power: i32| {
+        within()
+            .map(|round| round.iters as f64 * (round.time_per_iter_ns - mean_ns).powi(power))
+            .sum::<f64>()
+            / iters as f64
+    };
+    let variance = moment(2);
+    if variance <= 0.0 {
+        return 0.0;
+    }
+    let skewness = moment(3) / variance.powf(1.5);
+    let excess_kurtosis = moment(4) / variance.powi(2) - 3.0;
+    let n = rounds as f64;
+    (skewness.powi(2) + 1.0) / (excess_kurtosis + 3.0 * (n - 1.0).powi(2) / ((n - 2.0) * (n - 3.0)))
+}
+
+/// Returns the rounds left by the outlier policy, given the stats of all the rounds.
+/// `sorted_rounds` must be sorted by time.
+fn filter_outliers(
+    policy: OutlierPolicy,
+    sorted_rounds: &[WeightedRound],
+    total_iters: u64,
+    stats: &BenchmarkStats,
+) -> Vec<WeightedRound> {
+    let keep_within = |low: f64, high: f64| {
+        sorted_rounds
+            .iter()
+            .filter(|round| (low..=high).contains(&round.time_per_iter_ns))
+            .copied()
+            .collect()
+    };
+
+    match policy {
+        OutlierPolicy::None => sorted_rounds.to_vec(),
+        OutlierPolicy::Iqr => {
+            let iqr_ns = stats.q3_ns - stats.q1_ns;
+            keep_within(
+                stats.q1_ns - IQR_OUTLIER_FACTOR * iqr_ns,
+                stats.q3_ns + IQR_OUTLIER_FACTOR * iqr_ns,
+            )
+        }
+        OutlierPolicy::Mad if stats.mad_ns == 0.0 => sorted_rounds.to_vec(),
+        OutlierPolicy::Mad => {
+            let max_deviation_ns = MAD_OUTLIER_FACTOR * MAD_NORMAL_SCALE * stats.mad_ns;
+            keep_within(
+                stats.median_ns - max_deviation_ns,
+                stats.median_ns + max_deviation_ns,
+            )
+        }
+        OutlierPolicy::Winsorize => {
+            let low = weighted_quantile(sorted_rounds, total_iters, WINSORIZE_QUANTILE);
+            let high = weighted_quantile(sorted_rounds, total_iters, 1.0 - WINSORIZE_QUANTILE);
+            sorted_rounds
+                .iter()
+                .map(|round| WeightedRound {
+                    time_per_iter_ns: round.time_per_iter_ns.clamp(low, high),
+                    ..*round
+                })
+                .collect()
+        }
+    }
+}
+
+/// Time per iteration of one or more rounds, weighted by their number of iterations.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WeightedRound {
+    pub(crate) time_per_iter_ns: f64,
+    pub(crate) iters: u64,
+    pub(crate) rounds: u64,
+}
+
+/// Computes the quantile of the time per iteration over all the iterations, as if every
+/// iteration of a round took the mean time of the round. `rounds` must be sorted by time.
+///
+/// This uses the median-unbiased definition (R-8), so that the quantiles are the same as the
+/// ones previously computed with `statrs` when every round has a single iteration.
+pub(crate) fn weighted_quantile(rounds: &[WeightedRound], total_iters: u64, q: f64) -> f64 {
+    if total_iters == 0 {
+        return 0.0;
+    }
+
+    let value_at = |rank: u64| {
+        let mut cumulative_iters = 0;
+        for round in rounds {
+            cumulative_iters += round.iters;
+            if rank < cumulative_iters {
+                return round.time_per_iter_ns;
+            }
+        }
+        rounds.last().map_or(0.0, |round| round.time_per_iter_ns)
+    };
+
+    // 1-based rank of the quantile
+    let rank = (total_iters as f64 + 1.0 / 3.0) * q + 1.0 / 3.0;
+    if rank < 1.0 || q == 0.0 {
+        return value_at(0);
+    }
+    if rank >= total_iters as f64 || q == 1.0 {
+        return value_at(total_iters - 1);
+    }
+    let lower = value_at(rank as u64 - 1);
+    let upper = value_at(rank as u64);
+    lower + (upper - lower) * rank.fract()
+}
+
+/// Computes the percentile bootstrap confidence intervals of the mean and median: the rounds are
+/// resampled with replacement (see [`BOOTSTRAP_MAX_DRAWS`]), and the intervals are the central
+/// percentiles of the statistics of the resamples. `sorted_rounds` must be sorted by time.
+fn bootstrap(
+    sorted_rounds: &[WeightedRound],
+    rounds: u64,
+    mean_ns: f64,
+    median_ns: f64,
+    config: &StatsConfig,
+) -> Option<(ConfidenceInterval, ConfidenceInterval)> {
+    if rounds < 2 || config.bootstrap_resamples == 0 {
+        return None;
+    }
+
+    // Histogram buckets hold several rounds, so a round is drawn from the cumulative rounds
+    let cumulative_rounds: Vec<u64> = sorted_rounds
+        .iter()
+        .scan(0, |total, round| {
+            *total += round.rounds;
+            Some(*total)
+        })
+        .collect();
+    let iters_per_round: Vec<u64> = sorted_rounds
+        .iter()
+        .map(|round| (round.iters / round.rounds).max(1))
+        .collect();
+    let draws = rounds.min(BOOTSTRAP_MAX_DRAWS);
+    let scale = (draws as f64 / rounds as f64).sqrt();
+
+    let mut rng = SplitMix64(config.bootstrap_seed);
+    // Number of times every round is drawn, which avoids sorting the resample since the rounds
+    // are already sorted by time
+    let mut counts = vec![0u64; sorted_rounds.len()];
+    let mut means = Vec::with_capacity(config.bootstrap_resamples as usize);
+    let mut medians = Vec::with_capacity(config.bootstrap_resamples as usize);
+    for _ in 0..config.bootstrap_resamples {
+        counts.fill(0);
+        let mut total_iters = 0;
+        for _ in 0..draws {
+            let draw = rng.below(rounds);
+            let index = if cumulative_rounds.len() as u64 == rounds {
+                // Exact rounds, a round per index
+                draw as usize
+            } else {
+                cumulative_rounds.partition_point(|&total| total <= draw)
+            };
+            counts[index] += 1;
+            total_iters += iters_per_round[index];
+        }
+
+        // Weighted median, which is the same for every definition of `weighted_quantile`
+        let position = 0.5 * (total_iters - 1) as f64;
+        let (lower_rank, upper_rank) = (position.floor() as u64, position.ceil() as u64);
+        let (mut lower, mut upper) = (None, None);
+        let mut cumulative_iters = 0;
+        let mut total_time_ns = 0.0;
+        for ((round, &count), &iters) in sorted_rounds.iter().zip(&counts).zip(&iters_per_round) {
+            if count == 0 {
+                continue;
+            }
+            let iters = iters * count;
+            total_time_ns += round.time_per_iter_ns * iters as f64;
+            cumulative_iters += iters;
+            if lower.is_none() && lower_rank < cumulative_iters {
+                lower = Some(round.time_per_iter_ns);
+            }
+            if upper.is_none() && upper_rank < cumulative_iters {
+                upper = Some(round.time_per_iter_ns);
+            }
+        }
+        let (lower, upper) = (lower.unwrap_or_default(), upper.unwrap_or_default());
+
+        let mean = total_time_ns / total_iters as f64;
+        let median = lower + (upper - lower) * position.fract();
+        means.push(mean_ns + (mean - mean_ns) * scale);
+        medians.push(median_ns + (median - median_ns) * scale);
+    }
+
+    let level = config.confidence_level;
+    let interval = |mut estimates: Vec<f64>| {
+        estimates.sort_by(f64::total_cmp);
+        let alpha = (1.0 - level) / 2.0;
+        ConfidenceInterval {
+            level,
+            low_ns: percentile(&estimates, alpha),
+            high_ns: percentile(&estimates, 1.0 - alpha),
+        }
+    };
+    Some((interval(means), interval(medians)))
+}
+
+/// Percentile of sorted values, with linear interpolation between the closest ranks.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let lower = sorted[position.floor() as usize];
+    let upper = sorted[position.ceil() as usize];
+    lower + (upper - lower) * position.fract()
+}
+
+/// SplitMix64, a tiny PRNG whose sequence only depends on its seed, so that the bootstrap is
+/// reproducible across versions.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`, with a negligible bias for the bounds used here.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// Log-scale histogram of the time per iteration (like DDSketch): a value `x` falls in the
+/// bucket `ceil(log_gamma(x))`, whose midpoint is within the relative accuracy of every value of
+/// the bucket. The number of buckets only depends on the range of the values.
+#[derive(Debug)]
+struct Sketch {
+    gamma: f64,
+    ln_gamma: f64,
+    /// Iterations and rounds of the buckets `first_index..first_index + buckets.len()`.
+    first_index: i32,
+    buckets: Vec<(u64, u64)>,
+    /// Iterations and rounds whose time per iteration is 0, which has no bucket.
+    zero: (u64, u64),
+}
+
+impl Sketch {
+    fn new(relative_accuracy: f64) -> Self {
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        Sketch {
+            gamma,
+            ln_gamma: gamma.ln(),
+            first_index: 0,
+            buckets: Vec::new(),
+            zero: (0, 0),
+        }
+    }
+
+    fn insert(&mut self, round: WeightedRound) {
+        let bucket = if round.time_per_iter_ns > 0.0 {
+            let index = (round.time_per_iter_ns.ln() / self.ln_gamma).ceil() as i32;
+            if self.buckets.is_empty() {
+                self.first_index = index;
+            } else if index < self.first_index {
+                let missing = (self.first_index - index) as usize;
+                self.buckets
+                    .splice(0..0, std::iter::repeat_n((0, 0), missing));
+                self.first_index = index;
+            }
+            let position = (index - self.first_index) as usize;
+            if position >= self.buckets.len() {
+                self.buckets.resize(position + 1, (0, 0));
+            }
+            &mut self.buckets[position]
+        } else {
+            &mut self.zero
+        };
+        bucket.0 += round.iters;
+        bucket.1 += round.rounds;
+    }
+
+    /// Returns a round per bucket, sorted by time, at the midpoint of the bucket clamped to the
+    /// exact extrema.
+    fn into_rounds(self, min_ns: f64, max_ns: f64) -> Vec<WeightedRound> {
+        let buckets = self
+            .buckets
+            .into_iter()
+            .enumerate()
+            .map(|(position, bucket)| {
+                let index = self.first_index + position as i32;
+                (2.0 * self.gamma.powi(index) / (self.gamma + 1.0), bucket)
+            });
+        std::iter::once((0.0, self.zero))
+            .chain(buckets)
+            .filter(|(_, (iters, _))| *iters > 0)
+            .map(|(time_per_iter_ns, (iters, rounds))| WeightedRound {
+                time_per_iter_ns: time_per_iter_ns.clamp(min_ns, max_ns),
+                iters,
+                rounds,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_rounds(count: usize) -> (Vec<u64>, Vec<u64>) {
+        // xorshift, to get the same rounds on every run
+        let mut state: u64 = 42;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let iters: Vec<u64> = (0..count).map(|_| next() % 100 + 1).collect();
+        let times = iters
+            .iter()
+            .map(|&iters| iters * (1_000 + next() % 500) + next() % 50_000)
+            .collect();
+        (iters, times)
+    }
+
+    fn stats_of(iters: &[u64], times: &[u64], exact_max_rounds: usize) -> (BenchmarkStats, bool) {
+        let mut stats = RoundStats::with_exact_max_rounds(exact_max_rounds);
+        for (&time_ns, &iters) in times.iter().zip(iters) {
+            stats.push(time_ns, iters);
+        }
+        let approximate = stats.is_approximate();
+        (stats.finish(), approximate)
+    }
+
+    fn assert_relative_eq(actual: f64, expected: f64, tolerance: f64) {
+        let error = ((actual - expected) / expected).abs();
+        assert!(
+            error <= tolerance,
+            "{actual} isn't within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn test_weighted_quantile_matches_statrs() {
+        let rounds = |times: &[f64]| -> Vec<_> {
+            times
+                .iter()
+                .map(|&time_per_iter_ns| WeightedRound {
+                    time_per_iter_ns,
+                    iters: 1,
+                    rounds: 1,
+                })
+                .collect()
+        };
+        let assert_close = |actual: f64, expected: f64| {
+            assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+        };
+
+        // Values of `statrs::statistics::Data::quantile` (and `median`), used before
+        let four = rounds(&[1., 2., 3., 4.]);
+        assert_close(weighted_quantile(&four, 4, 0.0), 1.);
+        assert_close(weighted_quantile(&four, 4, 0.05), 1.);
+        assert_close(weighted_quantile(&four, 4, 0.25), 1.416_666_666_666_666_5);
+        assert_close(weighted_quantile(&four, 4, 0.5), 2.5);
+        assert_close(weighted_quantile(&four, 4, 0.75), 3.583_333_333_333_333_5);
+        assert_close(weighted_quantile(&four, 4, 0.95), 4.);
+        assert_close(weighted_quantile(&four, 4, 1.0), 4.);
+
+        let seven = rounds(&[10., 12., 15., 20., 21., 30., 100.]);
+        assert_close(weighted_quantile(&seven, 7, 0.25), 12.5);
+        assert_close(weighted_quantile(&seven, 7, 0.5), 20.);
+        assert_close(weighted_quantile(&seven, 7, 0.75), 28.5);
+    }
+
+    #[test]
+    fn test_weighted_quantile_counts_every_iteration() {
+        // A round of 2 iterations weighs as much as 2 rounds of 1 iteration
+        let weighted = [(1., 2), (3., 1), (4., 1)].map(|(time_per_iter_ns, iters)| WeightedRound {
+            time_per_iter_ns,
+            iters,
+            rounds: 1,
+        });
+        let unweighted = [1., 1., 3., 4.].map(|time_per_iter_ns| WeightedRound {
+            time_per_iter_ns,
+            iters: 1,
+            rounds: 1,
+        });
+        for q in [0.05, 0.25, 0.5, 0.75, 0.95] {
+            assert_eq!(
+                weighted_quantile(&weighted, 4, q),
+                weighted_quantile(&unweighted, 4, q),
+                "{q}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_rounds() {
+        let stats = RoundStats::new().finish();
+        assert_eq!(stats.rounds, 0);
+        assert_eq!(stats.min_ns, 0.);
+        assert_eq!(stats.median_ns, 0.);
+        assert_eq!(stats.iter_per_round_min, 0);
+        assert!(!stats.approximate_quantiles);
+    }
+
+    #[test]
+    fn test_rounds_without_iterations() {
+        let stats = RoundStats::from_rounds(&[0, 2], &[100, 20]).finish();
+        assert_eq!(stats.rounds, 1);
+        assert_eq!(stats.mean_ns, 10.);
+        assert_eq!(stats.iter_per_round_min, 0);
+        assert_eq!(stats.iter_per_round_mean, 1.);
+        assert_eq!(stats.total_time, 120. / 1_000_000_000.0);
+    }
+
+    #[test]
+    fn test_sketch_above_exact_max_rounds() {
+        let (iters, times) = random_rounds(10_000);
+        let (exact, approximate) = stats_of(&iters, &times, usize::MAX);
+        assert!(!approximate);
+        let (sketch, approximate) = stats_of(&iters, &times, 1_000);
+        assert!(approximate);
+        assert!(sketch.approximate_quantiles);
+
+        // Only the quantiles and outliers are estimated
+        assert_eq!(sketch.rounds, exact.rounds);
+        assert_eq!(sketch.min_ns, exact.min_ns);
+        assert_eq!(sketch.max_ns, exact.max_ns);
+        assert_eq!(sketch.mean_ns, exact.mean_ns);
+        assert_eq!(sketch.stdev_ns, exact.stdev_ns);
+        assert_eq!(sketch.total_time, exact.total_time);
+
+        assert_relative_eq(sketch.q1_ns, exact.q1_ns, SKETCH_RELATIVE_ACCURACY);
+        assert_relative_eq(sketch.median_ns, exact.median_ns, SKETCH_RELATIVE_ACCURACY);
+        assert_relative_eq(sketch.q3_ns, exact.q3_ns, SKETCH_RELATIVE_ACCURACY);
+        assert_relative_eq(
+            sketch.iqr_outlier_rounds as f64,
+            exact.iqr_outlier_rounds as f64,
+            0.05,
+        );
+    }
+
+    #[test]
+    fn test_exact_up_to_exact_max_rounds() {
+        let (iters, times) = random_rounds(1_000);
+        let (stats, approximate) = stats_of(&iters, &times, 1_000);
+        assert!(!approximate);
+        assert!(!stats.approximate_quantiles);
+    }
+
+    #[test]
+    fn test_variance_matches_two_pass_variance() {
+        let (iters, times) = random_rounds(1_000);
+        let (stats, _) = stats_of(&iters, &times, usize::MAX);
+
+        let total_iters: u64 = iters.iter().sum();
+        let weighted_sq_diff: f64 = times
+            .iter()
+            .zip(&iters)
+            .map(|(&time, &iters)| {
+                iters as f64 * (time as f64 / iters as f64 - stats.mean_ns).powi(2)
+            })
+            .sum();
+        let variance = weighted_sq_diff / total_iters as f64 * 1_000. / 999.;
+        assert_relative_eq(stats.stdev_ns, variance.sqrt(), 1e-9);
+    }
+
+    #[test]
+    fn test_mad_and_cv() {
+        let stats = RoundStats::from_rounds(&[1; 5], &[1, 2, 3, 4, 100]).finish();
+        assert_eq!(stats.median_ns, 3.);
+        // Deviations from the median: 0, 1, 1, 2, 97
+        assert_eq!(stats.mad_ns, 1.);
+        assert_relative_eq(stats.cv, stats.stdev_ns / stats.mean_ns, 1e-12);
+    }
+
+    #[test]
+    fn test_confidence_intervals() {
+        let (iters, times) = random_rounds(1_000);
+        let stats = RoundStats::from_rounds(&iters, &times).finish();
+
+        for (ci, estimate) in [
+            (stats.mean_ci.unwrap(), stats.mean_ns),
+            (stats.median_ci.unwrap(), stats.median_ns),
+        ] {
+            assert_eq!(ci.level, 0.95);
+            assert!(ci.low_ns < estimate && estimate < ci.high_ns, "{ci:?}");
+        }
+
+        // The bootstrap is deterministic, and only depends on the seed
+        let again = RoundStats::from_rounds(&iters, &times).finish();
+        assert_eq!(again.mean_ci, stats.mean_ci);
+        assert_eq!(again.median_ci, stats.median_ci);
+        let config = StatsConfig {
+            bootstrap_seed: 1,
+            ..Default::default()
+        };
+        let reseeded = RoundStats::from_rounds(&iters, &times)
+            .with_config(config)
+            .finish();
+        assert_ne!(reseeded.mean_ci, stats.mean_ci);
+
+        // A higher confidence level gives a wider interval
+        let config = StatsConfig {
+            confidence_level: 0.99,
+            ..Default::default()
+        };
+        let wider = RoundStats::from_rounds(&iters, &times)
+            .with_config(config)
+            .finish()
+            .mean_ci
+            .unwrap();
+        let mean_ci = stats.mean_ci.unwrap();
+        assert!(wider.low_ns < mean_ci.low_ns && mean_ci.high_ns < wider.high_ns);
+    }
+
+    #[test]
+    fn test_confidence_intervals_above_max_draws() {
+        let (iters, times) = random_rounds(4 * BOOTSTRAP_MAX_DRAWS as usize);
+        let (small_iters, small_times) = (
+            &iters[..BOOTSTRAP_MAX_DRAWS as usize],
+            &times[..BOOTSTRAP_MAX_DRAWS as usize],
+        );
+        let width = |stats: BenchmarkStats| {
+            let ci = stats.mean_ci.unwrap();
+            ci.high_ns - ci.low_ns
+        };
+
+        // The interval still shrinks like 1 / sqrt(rounds)
+        let ratio = width(RoundStats::from_rounds(small_iters, small_times).finish())
+            / width(RoundStats::from_rounds(&iters, &times).finish());
+        assert_relative_eq(ratio, 2., 0.2);
+    }
+
+    #[test]
+    fn test_confidence_intervals_unavailable() {
+        let stats = RoundStats::from_rounds(&[1], &[100]).finish();
+        assert!(stats.mean_ci.is_none());
+        assert!(stats.median_ci.is_none());
+
+        let config = StatsConfig {
+            bootstrap_resamples: 0,
+            ..Default::default()
+        };
+        let stats = RoundStats::from_rounds(&[1, 1], &[100, 200])
+            .with_config(config)
+            .finish();
+        assert!(stats.mean_ci.is_none());
+    }
+
+    fn filtered_stats(times: &[u64], outlier_policy: OutlierPolicy) -> BenchmarkStats {
+        let config = StatsConfig {
+            outlier_policy,
+            ..Default::default()
+        };
+        let stats = RoundStats::from_rounds(&vec![1; times.len()], times)
+            .with_config(config)
+            .finish();
+        // The stats of all the rounds are still reported
+        assert_eq!(stats.rounds, times.len() as u64);
+        assert_eq!(stats.max_ns, 1000.);
+        *stats.filtered.unwrap()
+    }
+
+    #[test]
+    fn test_outlier_policies() {
+        let times = [10, 11, 12, 13, 14, 1000];
+
+        let iqr = filtered_stats(&times, OutlierPolicy::Iqr);
+        assert_eq!(iqr.rounds, 5);
+        assert_eq!(iqr.max_ns, 14.);
+        assert_eq!(iqr.mean_ns, 12.);
+
+        // The MAD is 1.5, so rounds further than ~6.7ns from the median (12.5ns) are removed
+        let mad = filtered_stats(&times, OutlierPolicy::Mad);
+        assert_eq!(mad.rounds, 5);
+        assert_eq!(mad.max_ns, 14.);
+
+        // The 5th and 95th percentiles of 6 rounds are their min and max
+        let winsorized = filtered_stats(&times, OutlierPolicy::Winsorize);
+        assert_eq!(winsorized.rounds, 6);
+        assert_eq!(winsorized.max_ns, 1000.);
+
+        // The rounds are clamped to the 5th and 95th percentiles
+        let times: Vec<u64> = (10..49).chain([1000]).collect();
+        let winsorized = filtered_stats(&times, OutlierPolicy::Winsorize);
+        assert_eq!(winsorized.rounds, 40);
+        assert_relative_eq(winsorized.min_ns, 11.35, 1e-9);
+        assert_relative_eq(winsorized.max_ns, 47.65, 1e-9);
+
+        let stats = RoundStats::from_rounds(&[1; 6], &times).finish();
+        assert!(stats.filtered.is_none());
+    }
+
+    #[test]
+    fn test_mad_outlier_policy_without_deviation() {
+        // Most rounds have the same time, so the MAD can't tell the outliers apart
+        let mad = filtered_stats(&[10, 10, 10, 10, 1000], OutlierPolicy::Mad);
+        assert_eq!(mad.rounds, 5);
+    }
+
+    #[test]
+    fn test_drift() {
+        // The rounds get 1ns slower every round, from 100ns to 199ns
+        let times: Vec<u64> = (100..200).collect();
+        let stats = RoundStats::from_rounds(&vec![1; times.len()], &times).finish();
+        assert_relative_eq(stats.drift, 99. / 149.5, 1e-9);
+
+        let reversed: Vec<u64> = times.iter().rev().copied().collect();
+        let stats = RoundStats::from_rounds(&vec![1; times.len()], &reversed).finish();
+        assert_relative_eq(stats.drift, -99. / 149.5, 1e-9);
+
+        let stats = RoundStats::from_rounds(&[1; 4], &[100, 100, 100, 100]).finish();
+        assert_eq!(stats.drift, 0.);
+    }
+
+    #[test]
+    fn test_bimodality_coefficient() {
+        let (iters, times) = random_rounds(1_000);
+        let stats = RoundStats::from_rounds(&iters, &times).finish();
+        assert!(stats.bimodality_coefficient < 5. / 9., "{stats:?}");
+
+        let bimodal: Vec<u64> = (0..1_000).map(|i| 1000 + (i % 2) * 100).collect();
+        let stats = RoundStats::from_rounds(&[1; 1_000], &bimodal).finish();
+        assert!(stats.bimodality_coefficient > 0.9, "{stats:?}");
+
+        // Too few rounds
+        let stats = RoundStats::from_rounds(&[1; 3], &[1, 2, 100]).finish();
+        assert_eq!(stats.bimodality_coefficient, 0.);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::prelude::*;
+use crate::results::{
+    raw_result::{InvalidRawResult, ParsedBenchmark},
+    walltime_results::{BenchmarkStatus, UnmeasuredBenchmark, WalltimeBenchmark},
+};
+
+/// Summary of a benchmark run, logged once all the results have been collected.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    pub measured: usize,
+    pub failed: Vec<String>,
+    pub skipped: Vec<String>,
+    /// Measured benchmarks that were cut short by the time limit.
+    pub truncated: Vec<String>,
+    /// Raw results that couldn't be parsed.
+    pub invalid: Vec<String>,
+}
+
+impl RunSummary {
+    pub fn add(&mut self, benchmark: &ParsedBenchmark) {
+        match benchmark {
+            ParsedBenchmark::Measured(benchmark) => self.add_measured(benchmark),
+            ParsedBenchmark::Unmeasured(benchmark) => self.add_unmeasured(benchmark),
+        }
+    }
+
+    fn add_measured(&mut self, benchmark: &WalltimeBenchmark) {
+        self.measured += 1;
+        if let Some(truncation) = &benchmark.truncation {
+            self.truncated.push(format!(
+                "{} ({} of {} rounds, {})",
+                benchmark.metadata.uri,
+                truncation.actual_rounds,
+                truncation.planned_rounds,
+                truncation.reason
+            ));
+        }
+    }
+
+    fn add_unmeasured(&mut self, benchmark: &UnmeasuredBenchmark) {
+        let uri = benchmark.metadata.uri.clone();
+        match benchmark.status {
+            BenchmarkStatus::Failed => self.failed.push(uri),
+            BenchmarkStatus::Skipped => self.skipped.push(uri),
+            BenchmarkStatus::Ok => {}
+        }
+    }
+
+    pub fn add_invalid(&mut self, invalid: &InvalidRawResult) {
+        self.invalid.push(invalid.to_string());
+    }
+
+    pub fn log(&mut self) {
+        self.failed.sort();
+        self.skipped.sort();
+        self.truncated.sort();
+
+        info!(
+            "Run summary: {} measured, {} failed, {} skipped",
+            self.measured,
+            self.failed.len(),
+            self.skipped.len()
+        );
+        if !self.failed.is_empty() {
+            warn!("Failed benchmarks:\n  - {}", self.failed.join("\n  - "));
+        }
+        if !self.skipped.is_empty() {
+            info!("Skipped benchmarks:\n  - {}", self.skipped.join("\n  - "));
+        }
+        if !self.invalid.is_empty() {
+            error!(
+                "{} raw result(s) couldn't be parsed:\n  - {}",
+                self.invalid.len(),
+                self.invalid.join("\n  - ")
+            );
+        }
+        if !self.truncated.is_empty() {
+            warn!(
+                "{} benchmark(s) were cut short by the time limit, consider increasing \
+                 --max-time-multiplier:\n  - {}",
+                self.truncated.len(),
+                self.truncated.join("\n  - ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::walltime_results::{BenchmarkMetadata, Truncation};
+
+    fn unmeasured(uri: &str, status: BenchmarkStatus) -> ParsedBenchmark {
+        ParsedBenchmark::Unmeasured(UnmeasuredBenchmark {
+            metadata: BenchmarkMetadata::new(uri.into(), uri.into()),
+            status,
+            message: None,
+        })
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let measured =
+            WalltimeBenchmark::from_runtime_data("a".into(), "a".into(), &[1], &[42], 0, None);
+        let truncated =
+            WalltimeBenchmark::from_runtime_data("b".into(), "b".into(), &[1], &[42], 0, None)
+                .with_truncation(Some(Truncation {
+                    reason: "max_time_exceeded".into(),
+                    planned_rounds: 100,
+                    actual_rounds: 3,
+                }));
+
+        let mut summary = RunSummary::default();
+        for benchmark in [
+            ParsedBenchmark::Measured(Box::new(measured)),
+            ParsedBenchmark::Measured(Box::new(truncated)),
+            unmeasured("c", BenchmarkStatus::Failed),
+            unmeasured("d", BenchmarkStatus::Skipped),
+            unmeasured("e", BenchmarkStatus::Skipped),
+        ] {
+            summary.add(&benchmark);
+        }
+
+        assert_eq!(summary.measured, 2);
+        assert_eq!(summary.failed, vec!["c"]);
+        assert_eq!(summary.skipped, vec!["d", "e"]);
+        assert_eq!(
+            summary.truncated,
+            vec!["b (3 of 100 rounds, max_time_exceeded)"]
+        );
+    }
+}
@@ -1,52 +1,66 @@
 // NOTE: This file was taken from `codspeed-rust` and modified a bit to fit this project.
+//
+// The types of the results are defined in `results::schema`, this file computes them from the
+// measurements of the overlay.
 
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
-use statrs::statistics::{Data, Distribution, Max, Min, OrderStatistics};
+use crate::prelude::*;
+use crate::results::raw_result::RawRoundConfig;
+use crate::results::stats::RoundStats;
+use std::collections::BTreeMap;
 
-const IQR_OUTLIER_FACTOR: f64 = 1.5;
-const STDEV_OUTLIER_FACTOR: f64 = 3.0;
+pub use crate::results::schema::*;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BenchmarkMetadata {
-    pub name: String,
-    pub uri: String,
-}
+impl BenchmarkMetadata {
+    pub fn new(name: String, uri: String) -> Self {
+        let params = parse_params(&name);
+        BenchmarkMetadata {
+            name,
+            uri,
+            line: None,
+            package: None,
+            function: None,
+            params,
+            parallel: None,
+        }
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct BenchmarkStats {
-    min_ns: f64,
-    max_ns: f64,
-    mean_ns: f64,
-    stdev_ns: f64,
-
-    q1_ns: f64,
-    median_ns: f64,
-    q3_ns: f64,
-
-    rounds: u64,
-    total_time: f64,
-    iqr_outlier_rounds: u64,
-    stdev_outlier_rounds: u64,
-    iter_per_round: u64,
-    warmup_iters: u64,
+    /// Attaches where the benchmark function is defined. Empty values are ignored.
+    pub fn with_source(
+        mut self,
+        line: Option<u32>,
+        package: Option<String>,
+        function: Option<String>,
+    ) -> Self {
+        self.line = line.filter(|&line| line > 0);
+        self.package = package.filter(|package| !package.is_empty());
+        self.function = function.filter(|function| !function.is_empty());
+        self
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct BenchmarkConfig {
-    warmup_time_ns: Option<f64>,
-    min_round_time_ns: Option<f64>,
-    max_time_ns: Option<f64>,
-    max_rounds: Option<u64>,
+/// Parses the `key=value` segments of a benchmark name, whose sub-benchmarks are separated by
+/// `::`. The `#01` suffix added by Go to deduplicate sub-benchmark names is ignored, so that
+/// `size=1024#01` has the same parameters as `size=1024`. When a key is used multiple times, the
+/// innermost sub-benchmark wins.
+fn parse_params(name: &str) -> BTreeMap<String, String> {
+    name.split("::")
+        .map(strip_dedup_suffix)
+        .filter_map(|segment| segment.split_once('='))
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WalltimeBenchmark {
-    #[serde(flatten)]
-    pub metadata: BenchmarkMetadata,
-
-    config: BenchmarkConfig,
-    stats: BenchmarkStats,
+/// Strips the `#NN` suffixes that Go appends to duplicate sub-benchmark names. They can be nested
+/// when the deduplicated name is itself a duplicate (e.g. `a#01#01`).
+fn strip_dedup_suffix(mut segment: &str) -> &str {
+    while let Some((prefix, suffix)) = segment.rsplit_once('#') {
+        if suffix.is_empty() || !suffix.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        segment = prefix;
+    }
+    segment
 }
 
 impl WalltimeBenchmark {
@@ -55,116 +69,120 @@ impl WalltimeBenchmark {
         uri: String,
         iters_per_round: &[u64],
         times_per_round_ns: &[u64],
+        bytes_per_op: u64,
         max_time_ns: Option<u128>,
     ) -> Self {
-        let total_time =
-            times_per_round_ns.iter().map(|&t| t as u128).sum::<u128>() as f64 / 1_000_000_000.0;
-        let time_per_iteration_per_round_ns: Vec<_> = times_per_round_ns
-            .iter()
-            .zip(iters_per_round)
-            .map(|(time_per_round, iter_per_round)| {
-                *time_per_round as u128 / *iter_per_round as u128
-            })
-            .map(|t| t as f64)
-            .collect::<Vec<f64>>();
-
-        let mut data = Data::new(time_per_iteration_per_round_ns);
-        let rounds: u64 = data.len() as u64;
+        Self::from_round_stats(
+            name,
+            uri,
+            RoundStats::from_rounds(iters_per_round, times_per_round_ns),
+            bytes_per_op,
+            max_time_ns,
+        )
+    }
 
-        let mean_ns = data.mean().unwrap();
+    /// Like [`WalltimeBenchmark::from_runtime_data`], from rounds accumulated in `round_stats`.
+    pub fn from_round_stats(
+        name: String,
+        uri: String,
+        round_stats: RoundStats,
+        bytes_per_op: u64,
+        max_time_ns: Option<u128>,
+    ) -> Self {
+        let outlier_policy = round_stats.config().outlier_policy;
+        let mut stats = round_stats.finish();
 
-        let stdev_ns = if data.len() < 2 {
-            // std_dev() returns f64::NAN if data has less than two entries, so we have to
-            // manually handle this case.
-            0.0
-        } else {
-            data.std_dev().unwrap()
+        let bytes_per_op = (bytes_per_op > 0).then_some(bytes_per_op);
+        let mb_per_s = |time_ns: f64| {
+            bytes_per_op
+                .filter(|_| time_ns > 0.0)
+                .map(|bytes| bytes as f64 * 1e3 / time_ns)
         };
-
-        let q1_ns = data.quantile(0.25);
-        let median_ns = data.median();
-        let q3_ns = data.quantile(0.75);
-
-        let iqr_ns = q3_ns - q1_ns;
-        let iqr_outlier_rounds = data
-            .iter()
-            .filter(|&&t| {
-                t < q1_ns - IQR_OUTLIER_FACTOR * iqr_ns || t > q3_ns + IQR_OUTLIER_FACTOR * iqr_ns
-            })
-            .count() as u64;
-
-        let stdev_outlier_rounds = data
-            .iter()
-            .filter(|&&t| {
-                t < mean_ns - STDEV_OUTLIER_FACTOR * stdev_ns
-                    || t > mean_ns + STDEV_OUTLIER_FACTOR * stdev_ns
-            })
-            .count() as u64;
-
-        let min_ns = data.min();
-        let max_ns = data.max();
-
-        // TODO(COD-1056): We currently only support single iteration count per round
-        let iter_per_round = (iters_per_round.iter().map(|&t| t as u128).sum::<u128>()
-            / iters_per_round.len() as u128) as u64;
-        let warmup_iters = 0; // FIXME: add warmup detection
-
-        let stats = BenchmarkStats {
-            min_ns,
-            max_ns,
-            mean_ns,
-            stdev_ns,
-            q1_ns,
-            median_ns,
-            q3_ns,
-            rounds,
-            total_time,
-            iqr_outlier_rounds,
-            stdev_outlier_rounds,
-            iter_per_round,
-            warmup_iters,
+        let set_throughput = |stats: &mut BenchmarkStats| {
+            stats.mean_mb_per_s = mb_per_s(stats.mean_ns);
+            stats.median_mb_per_s = mb_per_s(stats.median_ns);
+            stats.bytes_per_op = bytes_per_op;
         };
+        set_throughput(&mut stats);
+        if let Some(filtered) = &mut stats.filtered {
+            set_throughput(filtered);
+        }
 
         WalltimeBenchmark {
-            metadata: BenchmarkMetadata { name, uri },
+            metadata: BenchmarkMetadata::new(name, uri),
             config: BenchmarkConfig {
                 max_time_ns: max_time_ns.map(|t| t as f64),
+                outlier_policy,
                 ..Default::default()
             },
             stats,
+            custom_metrics: BTreeMap::new(),
+            truncated: false,
+            truncation: None,
         }
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Instrument {
-    #[serde(rename = "type")]
-    type_: String,
-}
+    /// Attaches the warmup phase, executed before the measured rounds.
+    pub fn with_warmup(mut self, warmup_iters: u64, warmup_time_ns: u64) -> Self {
+        self.stats.warmup_iters = warmup_iters;
+        // Benchmarks using `b.Loop()` or a fixed iteration count don't have a warmup phase
+        self.config.warmup_time_ns = (warmup_iters > 0).then_some(warmup_time_ns as f64);
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Creator {
-    pub name: String,
-    pub version: String,
-    pub pid: u32,
-}
+        let total_time_ns = self.stats.total_time * 1_000_000_000.0;
+        if warmup_time_ns as f64 > total_time_ns {
+            warn!(
+                "Warmup of {} took longer than the benchmark itself ({:.2}ms > {:.2}ms)",
+                self.metadata.uri,
+                warmup_time_ns as f64 / 1_000_000.0,
+                total_time_ns / 1_000_000.0
+            );
+        }
+        self
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WalltimeResults {
-    creator: Creator,
-    instrument: Instrument,
-    pub benchmarks: Vec<WalltimeBenchmark>,
-}
+    /// See [`BenchmarkMetadata::with_source`].
+    pub fn with_source(
+        mut self,
+        line: Option<u32>,
+        package: Option<String>,
+        function: Option<String>,
+    ) -> Self {
+        self.metadata = self.metadata.with_source(line, package, function);
+        self
+    }
 
-impl WalltimeResults {
-    pub fn new(benchmarks: Vec<WalltimeBenchmark>, creator: Creator) -> Result<Self> {
-        Ok(WalltimeResults {
-            instrument: Instrument {
-                type_: "walltime".to_string(),
-            },
-            creator,
-            benchmarks,
-        })
+    /// Attaches the effective round strategy used by the overlay.
+    pub fn with_round_config(mut self, round_config: Option<RawRoundConfig>) -> Self {
+        if let Some(round_config) = round_config {
+            self.config.max_rounds = Some(round_config.max_rounds);
+            self.config.target_round_time_ns = Some(round_config.target_round_time_ns as f64);
+            self.config.max_time_ns = Some(round_config.max_time_ns as f64);
+        }
+        self
+    }
+
+    pub fn with_truncation(mut self, truncation: Option<Truncation>) -> Self {
+        self.truncated = truncation.is_some();
+        self.truncation = truncation;
+        self
+    }
+
+    /// Marks the benchmark as parallel, with the operations executed over all the rounds.
+    pub fn with_parallelism(mut self, parallelism: Option<Parallelism>, total_ops: u64) -> Self {
+        self.metadata.parallel = parallelism.map(|parallelism| Parallelism {
+            total_ops,
+            ..parallelism
+        });
+        self
+    }
+
+    /// Attaches the metrics reported with `b.ReportMetric` (unit -> value).
+    pub fn with_custom_metrics(mut self, extra: BTreeMap<String, f64>) -> Self {
+        self.custom_metrics = extra
+            .into_iter()
+            .map(|(unit, value)| (unit.clone(), CustomMetric::new(unit, value)))
+            .collect();
+        self
     }
 }
 
@@ -182,6 +200,7 @@ mod tests {
             URI.to_string(),
             &[1],
             &[42],
+            0,
             None,
         );
         assert_eq!(benchmark.stats.stdev_ns, 0.);
@@ -190,6 +209,218 @@ mod tests {
         assert_eq!(benchmark.stats.mean_ns, 42.);
     }
 
+    #[test]
+    fn test_parse_bench_with_outlier_policy() {
+        let config = crate::config::StatsConfig {
+            outlier_policy: OutlierPolicy::Iqr,
+            ..Default::default()
+        };
+        let round_stats = RoundStats::from_rounds(&[1; 5], &[1000, 1000, 1000, 1000, 100_000])
+            .with_config(config);
+        let benchmark =
+            WalltimeBenchmark::from_round_stats(NAME.into(), URI.into(), round_stats, 1000, None);
+
+        assert_eq!(benchmark.config.outlier_policy, OutlierPolicy::Iqr);
+        assert_eq!(benchmark.stats.rounds, 5);
+        assert_eq!(benchmark.stats.mean_ns, 20_800.);
+        let filtered = benchmark.stats.filtered().unwrap();
+        assert_eq!(filtered.rounds, 4);
+        assert_eq!(filtered.mean_ns, 1000.);
+        assert_eq!(filtered.mean_mb_per_s, Some(1000.));
+        assert!(filtered.filtered.is_none());
+
+        // Without a policy, only the stats of all the rounds are reported
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.into(),
+            URI.into(),
+            &[1; 5],
+            &[1000, 1000, 1000, 1000, 100_000],
+            0,
+            None,
+        );
+        assert_eq!(benchmark.config.outlier_policy, OutlierPolicy::None);
+        assert!(benchmark.stats.filtered.is_none());
+    }
+
+    #[test]
+    fn test_parse_bench_with_bytes() {
+        // 3 iterations at 500ns and 1 iteration at 1000ns: 625ns on average, 500ns median
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[2, 1, 1],
+            &[1000, 1000, 500],
+            1000,
+            None,
+        );
+
+        assert_eq!(benchmark.stats.bytes_per_op, Some(1000));
+        assert_eq!(benchmark.stats.mean_mb_per_s, Some(1600.));
+        assert_eq!(benchmark.stats.median_mb_per_s, Some(2000.));
+
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        );
+        assert_eq!(benchmark.stats.bytes_per_op, None);
+        assert_eq!(benchmark.stats.mean_mb_per_s, None);
+    }
+
+    #[test]
+    fn test_parse_bench_with_warmup() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        );
+        assert_eq!(benchmark.stats.warmup_iters, 0);
+        assert_eq!(benchmark.config.warmup_time_ns, None);
+
+        let benchmark = benchmark.with_warmup(100, 4_200);
+        assert_eq!(benchmark.stats.warmup_iters, 100);
+        assert_eq!(benchmark.config.warmup_time_ns, Some(4_200.));
+    }
+
+    #[test]
+    fn test_parse_bench_with_round_config() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        )
+        .with_round_config(Some(RawRoundConfig {
+            max_rounds: 100,
+            target_round_time_ns: 30_000_000,
+            max_time_ns: 9_000_000_000,
+        }));
+
+        assert_eq!(benchmark.config.max_rounds, Some(100));
+        assert_eq!(benchmark.config.target_round_time_ns, Some(30_000_000.));
+        // The overlay doesn't enforce a minimum round time
+        assert_eq!(benchmark.config.min_round_time_ns, None);
+        assert_eq!(benchmark.config.max_time_ns, Some(9_000_000_000.));
+    }
+
+    #[test]
+    fn test_truncated_benchmark() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        );
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert!(json.get("truncated").is_none());
+        assert!(json.get("truncation").is_none());
+
+        let benchmark = benchmark.with_truncation(Some(Truncation {
+            reason: "max_time_exceeded".into(),
+            planned_rounds: 100,
+            actual_rounds: 12,
+        }));
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert_eq!(json["truncated"], true);
+        assert_eq!(
+            json["truncation"],
+            serde_json::json!({
+                "reason": "max_time_exceeded",
+                "planned_rounds": 100,
+                "actual_rounds": 12,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parallel_benchmark() {
+        let parallelism = Parallelism {
+            goroutines: 16,
+            parallelism: 2,
+            gomaxprocs: 8,
+            total_ops: 0,
+        };
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[100, 100],
+            &[1000, 1000],
+            0,
+            None,
+        )
+        .with_parallelism(Some(parallelism), 200);
+
+        let parallel = benchmark.metadata.parallel.as_ref().unwrap();
+        assert_eq!(parallel.goroutines, 16);
+        assert_eq!(parallel.total_ops, 200);
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert_eq!(json["parallel"]["parallelism"], 2);
+    }
+
+    #[test]
+    fn test_sequential_benchmark_has_no_parallelism() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        )
+        .with_parallelism(None, 0);
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert!(json.get("parallel").is_none());
+    }
+
+    #[test]
+    fn test_custom_metrics() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        )
+        .with_custom_metrics(BTreeMap::from([
+            ("msgs/s".to_string(), 1234.5),
+            ("allocs/op".to_string(), 2.0),
+        ]));
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert_eq!(
+            json["custom_metrics"],
+            serde_json::json!({
+                "allocs/op": {"value": 2.0, "unit": "allocs/op", "aggregation": "per_op"},
+                "msgs/s": {"value": 1234.5, "unit": "msgs/s", "aggregation": "per_benchmark"},
+            })
+        );
+
+        // Benchmarks without custom metrics keep the same output as before
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1],
+            &[42],
+            0,
+            None,
+        );
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert!(json.get("custom_metrics").is_none());
+    }
+
     #[test]
     fn test_parse_bench_with_variable_iterations() {
         let iters_per_round = vec![1, 2, 3, 4, 5, 6];
@@ -200,6 +431,7 @@ mod tests {
             URI.to_string(),
             &iters_per_round,
             &[42, 42 * 2, 42 * 3, 42 * 4, 42 * 5, 42 * 6],
+            0,
             None,
         );
 
@@ -212,4 +444,144 @@ mod tests {
             42. * total_rounds / 1_000_000_000.0
         );
     }
+
+    #[test]
+    fn test_parse_bench_with_sub_nanosecond_iterations() {
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[1_000, 1_000],
+            &[250, 750],
+            0,
+            None,
+        );
+
+        assert_eq!(benchmark.stats.min_ns, 0.25);
+        assert_eq!(benchmark.stats.max_ns, 0.75);
+        assert_eq!(benchmark.stats.mean_ns, 0.5);
+    }
+
+    #[test]
+    fn test_parse_bench_with_weighted_rounds() {
+        // 9 iterations at 10ns and a single slow iteration at 100ns
+        let benchmark = WalltimeBenchmark::from_runtime_data(
+            NAME.to_string(),
+            URI.to_string(),
+            &[9, 1],
+            &[90, 100],
+            0,
+            None,
+        );
+
+        assert_eq!(benchmark.stats.mean_ns, 19.);
+        assert_eq!(benchmark.stats.median_ns, 10.);
+        assert_eq!(benchmark.stats.q1_ns, 10.);
+        assert_eq!(benchmark.stats.q3_ns, 10.);
+        assert_eq!(benchmark.stats.min_ns, 10.);
+        assert_eq!(benchmark.stats.max_ns, 100.);
+        assert_eq!(benchmark.stats.iqr_outlier_rounds, 1);
+
+        assert_eq!(benchmark.stats.iter_per_round, 5);
+        assert_eq!(benchmark.stats.iter_per_round_min, 1);
+        assert_eq!(benchmark.stats.iter_per_round_max, 9);
+        assert_eq!(benchmark.stats.iter_per_round_mean, 5.);
+    }
+
+    #[test]
+    fn test_deserialize_stats_without_iteration_distribution() {
+        let json = r#"{
+            "min_ns": 1.0, "max_ns": 1.0, "mean_ns": 1.0, "stdev_ns": 0.0,
+            "q1_ns": 1.0, "median_ns": 1.0, "q3_ns": 1.0,
+            "rounds": 1, "total_time": 1.0, "iqr_outlier_rounds": 0,
+            "stdev_outlier_rounds": 0, "iter_per_round": 3, "warmup_iters": 0
+        }"#;
+        let stats: BenchmarkStats = serde_json::from_str(json).unwrap();
+        assert_eq!(stats.iter_per_round, 3);
+        assert_eq!(stats.iter_per_round_mean, 0.);
+    }
+
+    #[test]
+    fn test_serialize_unmeasured_benchmarks() {
+        let creator = Creator {
+            name: "codspeed-go".into(),
+            version: "0.0.0".into(),
+            pid: 1,
+        };
+        let mut results = WalltimeResults::new(vec![], creator).unwrap();
+        let json = serde_json::to_value(&results).unwrap();
+        assert!(json.get("unmeasured_benchmarks").is_none());
+
+        results.unmeasured_benchmarks.push(UnmeasuredBenchmark {
+            metadata: BenchmarkMetadata::new(NAME.into(), URI.into()),
+            status: BenchmarkStatus::Skipped,
+            message: None,
+        });
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(
+            json["unmeasured_benchmarks"],
+            serde_json::json!([{ "name": NAME, "uri": URI, "status": "skipped" }])
+        );
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = parse_params("BenchmarkCodec::size=1024::codec=json");
+        assert_eq!(
+            params,
+            BTreeMap::from([
+                ("codec".to_string(), "json".to_string()),
+                ("size".to_string(), "1024".to_string()),
+            ])
+        );
+
+        assert!(parse_params("BenchmarkFoo::small").is_empty());
+        assert!(parse_params("BenchmarkFoo::=1").is_empty());
+        assert_eq!(parse_params("BenchmarkFoo::expr=a=b")["expr"], "a=b");
+        assert_eq!(parse_params("BenchmarkFoo::n=1::n=2")["n"], "2");
+    }
+
+    #[test]
+    fn test_parse_params_ignores_dedup_suffix() {
+        assert_eq!(parse_params("BenchmarkFoo::size=1024#01")["size"], "1024");
+        assert_eq!(
+            parse_params("BenchmarkFoo::size=1024#01#02")["size"],
+            "1024"
+        );
+        assert_eq!(parse_params("BenchmarkFoo::id=#1a")["id"], "#1a");
+        assert_eq!(parse_params("BenchmarkFoo::id=a#")["id"], "a#");
+    }
+
+    #[test]
+    fn test_params_serialization() {
+        let metadata = BenchmarkMetadata::new("BenchmarkFoo::size=1".into(), URI.into());
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["params"], serde_json::json!({ "size": "1" }));
+
+        let metadata = BenchmarkMetadata::new(NAME.into(), URI.into());
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert!(json.get("params").is_none());
+    }
+
+    #[test]
+    fn test_metadata_source() {
+        let metadata = BenchmarkMetadata::new(NAME.into(), URI.into()).with_source(
+            Some(12),
+            Some("example.com/pkg".into()),
+            Some("example.com/pkg_test.BenchmarkFoo".into()),
+        );
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["line"], 12);
+        assert_eq!(json["package"], "example.com/pkg");
+        assert_eq!(json["function"], "example.com/pkg_test.BenchmarkFoo");
+
+        let metadata = BenchmarkMetadata::new(NAME.into(), URI.into()).with_source(
+            Some(0),
+            Some(String::new()),
+            None,
+        );
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert!(json.get("line").is_none());
+        assert!(json.get("package").is_none());
+        assert!(json.get("function").is_none());
+    }
 }
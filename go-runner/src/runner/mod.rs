@@ -1,4 +1,6 @@
 use crate::cli::Cli;
+use crate::config::Config;
+use crate::hooks::HooksServer;
 use crate::prelude::*;
 use std::{
     path::{Path, PathBuf},
@@ -8,6 +10,8 @@ use tempfile::TempDir;
 
 mod overlay;
 
+use overlay::HooksBackend;
+
 fn check_c_compiler(go_binary: &Path) -> anyhow::Result<()> {
     let output = Command::new(go_binary)
         .args(["env", "CC"])
@@ -21,29 +25,93 @@ fn check_c_compiler(go_binary: &Path) -> anyhow::Result<()> {
 
     let cc = String::from_utf8_lossy(&output.stdout).trim().to_string();
     if cc.is_empty() {
-        bail!(
-            "No C compiler found. The CodSpeed Go runner requires a C compiler (gcc/cc) \
-             to build the instrumentation hooks. Install `build-essential` on Ubuntu/Debian \
-             or the equivalent for your platform."
-        );
+        bail!("No C compiler found");
+    }
+
+    // `go env CC` returns the default compiler even if it's not installed, so we have to
+    // check that it actually exists.
+    let cc_binary = cc.split_whitespace().next().unwrap_or_default();
+    if find_executable(cc_binary).is_none() {
+        bail!("C compiler `{cc_binary}` not found");
     }
 
     Ok(())
 }
 
+/// Whether cgo is disabled, either in the environment or with `go env -w CGO_ENABLED=0`.
+fn is_cgo_disabled(go_binary: &Path) -> bool {
+    let output = Command::new(go_binary)
+        .args(["env", "CGO_ENABLED"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim() == "0"
+        }
+        _ => {
+            warn!("Failed to determine whether cgo is enabled via `go env CGO_ENABLED`");
+            std::env::var("CGO_ENABLED").is_ok_and(|value| value == "0")
+        }
+    }
+}
+
+fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Selects the instrument hooks that can be built in the current environment. The C bindings
+/// are preferred, and the pure-Go implementation is used when cgo isn't usable.
+fn select_hooks_backend(go_binary: &Path, local_hooks: bool) -> HooksBackend {
+    if local_hooks {
+        // The C library always connects to the FIFOs of the CodSpeed runner
+        info!("Using the pure-Go instrument hooks to receive their events locally");
+        return HooksBackend::PureGo;
+    }
+
+    if is_cgo_disabled(go_binary) {
+        info!("CGO_ENABLED=0 is set, using the pure-Go instrument hooks");
+        return HooksBackend::PureGo;
+    }
+
+    if let Err(error) = check_c_compiler(go_binary) {
+        warn!(
+            "{error:#}. Using the pure-Go instrument hooks instead, install `build-essential` on \
+             Ubuntu/Debian or the equivalent for your platform to use the C implementation."
+        );
+        return HooksBackend::PureGo;
+    }
+
+    HooksBackend::Cgo
+}
+
 fn run_cmd<P: AsRef<Path>>(
     profile_dir: P,
     dir: P,
     cli: &Cli,
+    config: &Config,
+    local_hooks: Option<&HooksServer>,
 ) -> anyhow::Result<(TempDir, Command)> {
     // Execute the `go test` command using the go binary, rather than the one in the PATH
     // to avoid running into infinite loops with the runner which tries to intercept `go test`.
     let go_binary = find_go_binary()?;
 
     // Check early, before downloading instrument-hooks and generating the overlay.
-    check_c_compiler(&go_binary)?;
+    let hooks_backend = select_hooks_backend(&go_binary, local_hooks.is_some());
 
-    let (_dir, overlay_file) = overlay::get_overlay_file(profile_dir.as_ref())?;
+    let (_dir, overlay_file) = overlay::get_overlay_file(
+        profile_dir.as_ref(),
+        dir.as_ref(),
+        hooks_backend,
+        local_hooks,
+        cli,
+        config,
+    )?;
 
     // Convert the CLI struct into a command:
     let mut cmd = Command::new(go_binary);
@@ -70,10 +138,12 @@ fn run_cmd<P: AsRef<Path>>(
     cmd.env("GOCACHE", _dir.path().join("gocache"));
     cmd.env("GOMODCACHE", _dir.path().join("gomodcache"));
 
-    // The overlay includes instrument-hooks.go which uses cgo (`import "C"`).
-    // If CGO_ENABLED=0 (e.g. no C compiler on a bare metal runner), Go silently
-    // excludes the file, causing "undefined: InstrumentHooks" build errors.
-    cmd.env("CGO_ENABLED", "1");
+    // The C bindings of the instrument hooks use cgo (`import "C"`). If CGO_ENABLED=0,
+    // Go silently excludes the file, causing "undefined: InstrumentHooks" build errors.
+    match hooks_backend {
+        HooksBackend::Cgo => cmd.env("CGO_ENABLED", "1"),
+        HooksBackend::PureGo => cmd.env("CGO_ENABLED", "0"),
+    };
 
     Ok((_dir, cmd))
 }
@@ -101,15 +171,23 @@ pub fn run_with_stdout<P: AsRef<Path>>(
     profile_dir: P,
     dir: P,
     cli: &Cli,
+    config: &Config,
 ) -> anyhow::Result<String> {
-    let (_dir, mut cmd) = run_cmd(profile_dir, dir, cli)?;
+    let (_dir, mut cmd) = run_cmd(profile_dir, dir, cli, config, None)?;
     let output = cmd.output().context("Failed to execute go build command")?;
     check_success(&output)
 }
 
-/// Runs the cmd and forwards the output to stdout/stderr.
-pub fn run<P: AsRef<Path>>(profile_dir: P, dir: P, cli: &Cli) -> anyhow::Result<()> {
-    let (_dir, mut cmd) = run_cmd(profile_dir, dir, cli)?;
+/// Runs the cmd and forwards the output to stdout/stderr. With `local_hooks`, the instrument hooks
+/// send their events to the given server instead of the CodSpeed runner.
+pub fn run<P: AsRef<Path>>(
+    profile_dir: P,
+    dir: P,
+    cli: &Cli,
+    config: &Config,
+    local_hooks: Option<&HooksServer>,
+) -> anyhow::Result<()> {
+    let (_dir, mut cmd) = run_cmd(profile_dir, dir, cli, config, local_hooks)?;
     let output = cmd
         .stdout(std::process::Stdio::inherit())
         .stderr(std::process::Stdio::inherit())
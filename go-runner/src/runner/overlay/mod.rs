@@ -1,4 +1,7 @@
-use anyhow::{bail, ensure};
+use crate::cli::{Cli, UriRoot};
+use crate::config::Config;
+use crate::hooks::{HooksServer, protocol};
+use anyhow::{Context, bail, ensure};
 use semver::Version;
 use std::{
     collections::HashMap,
@@ -8,20 +11,42 @@ use std::{
 use tempfile::TempDir;
 
 mod instrument_hooks;
+mod template;
 
-const OVERLAY_TEMPLATES: &[(&str, &str)] = &[
-    ("codspeed.go", include_str!("../../../overlay/codspeed.go")),
-    (
-        "instrument-hooks.go",
-        include_str!("../../../overlay/instrument-hooks.go"),
-    ),
-];
+const CODSPEED_TEMPLATE: &str = include_str!("../../../overlay/codspeed.go");
+
+/// Implementation of the `InstrumentHooks` API that is compiled into the overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HooksBackend {
+    /// Bindings to the instrument-hooks C library, which requires cgo.
+    Cgo,
+    /// Pure-Go implementation of the instrument-hooks protocol, used when cgo isn't usable.
+    PureGo,
+}
+
+impl HooksBackend {
+    fn template(&self) -> &'static str {
+        match self {
+            HooksBackend::Cgo => include_str!("../../../overlay/instrument-hooks.go"),
+            HooksBackend::PureGo => include_str!("../../../overlay/instrument-hooks-purego.go"),
+        }
+    }
+}
 
 fn get_overlay_files(
     profile_dir: &Path,
+    project_dir: &Path,
     temp_dir: &TempDir,
+    hooks_backend: HooksBackend,
+    local_hooks: Option<&HooksServer>,
+    cli: &Cli,
+    config: &Config,
 ) -> anyhow::Result<HashMap<String, String>> {
-    let instrument_hooks_dir = instrument_hooks::download_instrument_hooks(temp_dir)?;
+    // The pure-Go hooks don't need the C library
+    let instrument_hooks_dir = match hooks_backend {
+        HooksBackend::Cgo => Some(instrument_hooks::download_instrument_hooks(temp_dir)?),
+        HooksBackend::PureGo => None,
+    };
 
     let mut files = HashMap::new();
 
@@ -33,28 +58,131 @@ fn get_overlay_files(
     };
     files.insert("benchmark.go".to_string(), content.to_string());
 
-    // Add other overlay files
-    for (file_name, content) in OVERLAY_TEMPLATES {
-        let content = content
-            .replace(
-                "@@INSTRUMENT_HOOKS_DIR@@",
-                &instrument_hooks_dir.to_string_lossy(),
-            )
-            .replace("@@CODSPEED_PROFILE_DIR@@", &profile_dir.to_string_lossy())
-            .replace("@@GO_RUNNER_VERSION@@", env!("CARGO_PKG_VERSION"));
-        files.insert(file_name.to_string(), content);
-    }
+    let uri_root = resolve_uri_root(&cli.uri_root, project_dir)?;
+    files.extend(render_overlay_templates(
+        profile_dir,
+        hooks_backend,
+        instrument_hooks_dir.as_deref(),
+        local_hooks,
+        &uri_root,
+        config,
+    )?);
     Ok(files)
 }
 
-pub fn get_overlay_file(profile_dir: &Path) -> anyhow::Result<(TempDir, PathBuf)> {
+/// Resolves explicit URI roots to absolute paths, since the benchmarks are executed in their
+/// package directory.
+fn resolve_uri_root(uri_root: &UriRoot, project_dir: &Path) -> anyhow::Result<UriRoot> {
+    let UriRoot::Path(path) = uri_root else {
+        return Ok(uri_root.clone());
+    };
+
+    // Also catches typos of `git` and `module`
+    let path = project_dir.join(path);
+    let path = path.canonicalize().with_context(|| {
+        format!("Invalid URI root {path:?}, expected 'git', 'module' or an existing directory")
+    })?;
+    ensure!(path.is_dir(), "URI root is not a directory: {path:?}");
+    Ok(UriRoot::Path(path))
+}
+
+fn render_overlay_templates(
+    profile_dir: &Path,
+    hooks_backend: HooksBackend,
+    instrument_hooks_dir: Option<&Path>,
+    local_hooks: Option<&HooksServer>,
+    uri_root: &UriRoot,
+    config: &Config,
+) -> anyhow::Result<HashMap<String, String>> {
+    let (uri_root_mode, uri_root) = match uri_root {
+        UriRoot::Git => ("git", String::new()),
+        UriRoot::Module => ("module", String::new()),
+        UriRoot::Path(path) => ("path", path_to_string(path)?),
+    };
+
+    let (ctl_fifo, ack_fifo) = match local_hooks {
+        Some(server) => (
+            path_to_string(server.ctl_fifo())?,
+            path_to_string(server.ack_fifo())?,
+        ),
+        None => (
+            protocol::RUNNER_CTL_FIFO.to_string(),
+            protocol::RUNNER_ACK_FIFO.to_string(),
+        ),
+    };
+
+    let round_strategy = &config.rounds;
+    let mut values = HashMap::from([
+        ("CODSPEED_PROFILE_DIR", path_to_string(profile_dir)?),
+        ("GO_RUNNER_VERSION", env!("CARGO_PKG_VERSION").to_string()),
+        ("CODSPEED_LOCAL_HOOKS", local_hooks.is_some().to_string()),
+        ("CODSPEED_HOOKS_CTL_FIFO", ctl_fifo),
+        ("CODSPEED_HOOKS_ACK_FIFO", ack_fifo),
+        ("CODSPEED_URI_ROOT_MODE", uri_root_mode.to_string()),
+        ("CODSPEED_URI_ROOT", uri_root),
+        ("CODSPEED_MIN_ROUNDS", round_strategy.min_rounds.to_string()),
+        (
+            "CODSPEED_WARMUP_FRACTION",
+            round_strategy.warmup_fraction.to_string(),
+        ),
+        (
+            "CODSPEED_MAX_TIME_MULTIPLIER",
+            round_strategy.max_time_multiplier.to_string(),
+        ),
+        (
+            "CODSPEED_RAW_RESULTS_TRANSPORT",
+            config.results.raw_results_transport.as_str().to_string(),
+        ),
+    ]);
+    if let Some(instrument_hooks_dir) = instrument_hooks_dir {
+        values.insert(
+            "INSTRUMENT_HOOKS_DIR",
+            path_to_string(instrument_hooks_dir)?,
+        );
+    }
+
+    let templates = [
+        ("codspeed.go", CODSPEED_TEMPLATE),
+        ("instrument-hooks.go", hooks_backend.template()),
+    ];
+    templates
+        .iter()
+        .map(|(file_name, content)| {
+            let content = template::render(file_name, content, &values)?;
+            Ok((file_name.to_string(), content))
+        })
+        .collect()
+}
+
+fn path_to_string(path: &Path) -> anyhow::Result<String> {
+    path.to_str()
+        .map(str::to_string)
+        .with_context(|| format!("Path is not valid UTF-8: {path:?}"))
+}
+
+pub fn get_overlay_file(
+    profile_dir: &Path,
+    project_dir: &Path,
+    hooks_backend: HooksBackend,
+    local_hooks: Option<&HooksServer>,
+    cli: &Cli,
+    config: &Config,
+) -> anyhow::Result<(TempDir, PathBuf)> {
     let overlay_dir = TempDir::new()?;
     let goroot_dir = find_goroot()?.join("src").join("testing");
     ensure!(goroot_dir.exists(), "GOROOT/src/testing does not exist");
 
     // Put all the overlay files into $GOROOT/src/testing
     let mut replaces = HashMap::new();
-    for (file_name, content) in get_overlay_files(profile_dir, &overlay_dir)? {
+    for (file_name, content) in get_overlay_files(
+        profile_dir,
+        project_dir,
+        &overlay_dir,
+        hooks_backend,
+        local_hooks,
+        cli,
+        config,
+    )? {
         let real_path = goroot_dir.join(&file_name);
         let patch_path = overlay_dir.path().join(&file_name);
 
@@ -108,3 +236,196 @@ fn detect_go_version() -> anyhow::Result<Version> {
 
     Ok(Version::parse(version_str)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RawResultsTransport, ResultsConfig, RoundStrategy};
+
+    #[test]
+    fn test_render_overlay_templates_with_hostile_profile_dir() {
+        let files = render_overlay_templates(
+            Path::new("/tmp/profile \"dir\"\\with\nnewline"),
+            HooksBackend::Cgo,
+            Some(Path::new("/tmp/instrument hooks")),
+            None,
+            &UriRoot::Git,
+            &Config::default(),
+        )
+        .unwrap();
+
+        let codspeed = &files["codspeed.go"];
+        assert!(codspeed.contains(r#""/tmp/profile \"dir\"\\with\nnewline""#));
+
+        let hooks = &files["instrument-hooks.go"];
+        assert!(hooks.contains(r"-I/tmp/instrument\ hooks/includes"));
+        assert!(hooks.contains(r#"#include "/tmp/instrument hooks/dist/core.c""#));
+
+        for content in files.values() {
+            assert!(!content.contains("@@"));
+        }
+    }
+
+    #[test]
+    fn test_render_overlay_templates_with_pure_go_hooks() {
+        let files = render_overlay_templates(
+            Path::new("/tmp/profile"),
+            HooksBackend::PureGo,
+            None,
+            None,
+            &UriRoot::Git,
+            &Config::default(),
+        )
+        .unwrap();
+
+        let hooks = &files["instrument-hooks.go"];
+        assert!(!hooks.contains("import \"C\""));
+        assert!(files["codspeed.go"].contains(r#"var codspeedProfileDir = "/tmp/profile""#));
+        assert!(hooks.contains(&format!(
+            r#"var integrationVersion = "{}""#,
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(hooks.contains(r#"runnerCtlFifo = "/tmp/runner.ctl.fifo""#));
+        assert!(files["codspeed.go"].contains(r#"codspeedLocalHooks = "false" == "true""#));
+    }
+
+    #[test]
+    fn test_render_overlay_templates_with_local_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = HooksServer::start_at(
+            &temp_dir.path().join("ctl.fifo"),
+            &temp_dir.path().join("ack.fifo"),
+        )
+        .unwrap();
+        let files = render_overlay_templates(
+            Path::new("/tmp/profile"),
+            HooksBackend::PureGo,
+            None,
+            Some(&server),
+            &UriRoot::Git,
+            &Config::default(),
+        )
+        .unwrap();
+        server.stop().unwrap();
+
+        let hooks = &files["instrument-hooks.go"];
+        assert!(hooks.contains(&format!(
+            r#"runnerCtlFifo = "{}""#,
+            temp_dir.path().join("ctl.fifo").display()
+        )));
+        assert!(hooks.contains(&format!(
+            r#"runnerAckFifo = "{}""#,
+            temp_dir.path().join("ack.fifo").display()
+        )));
+        assert!(files["codspeed.go"].contains(r#"codspeedLocalHooks = "true" == "true""#));
+    }
+
+    #[test]
+    fn test_render_overlay_templates_cgo_requires_instrument_hooks_dir() {
+        let result = render_overlay_templates(
+            Path::new("/tmp/profile"),
+            HooksBackend::Cgo,
+            None,
+            None,
+            &UriRoot::Git,
+            &Config::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_overlay_templates_with_uri_root() {
+        let files = render_overlay_templates(
+            Path::new("/tmp/profile"),
+            HooksBackend::PureGo,
+            None,
+            None,
+            &UriRoot::Path(PathBuf::from("/src/my \"repo\"")),
+            &Config::default(),
+        )
+        .unwrap();
+
+        let codspeed = &files["codspeed.go"];
+        assert!(codspeed.contains(r#"codspeedUriRootMode = "path""#));
+        assert!(codspeed.contains(r#"codspeedUriRoot     = "/src/my \"repo\"""#));
+    }
+
+    #[test]
+    fn test_render_overlay_templates_with_round_strategy() {
+        let files = render_overlay_templates(
+            Path::new("/tmp/profile"),
+            HooksBackend::PureGo,
+            None,
+            None,
+            &UriRoot::Git,
+            &Config {
+                rounds: RoundStrategy {
+                    min_rounds: 500,
+                    warmup_fraction: 0.25,
+                    max_time_multiplier: 5.0,
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let codspeed = &files["codspeed.go"];
+        assert!(codspeed.contains(r#"mustParseInt("500")"#));
+        assert!(codspeed.contains(r#"mustParseFloat("0.25")"#));
+        assert!(codspeed.contains(r#"mustParseFloat("5")"#));
+    }
+
+    #[test]
+    fn test_render_overlay_templates_with_raw_results_transport() {
+        let render = |raw_results_transport| {
+            let config = Config {
+                results: ResultsConfig {
+                    raw_results_transport,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            render_overlay_templates(
+                Path::new("/tmp/profile"),
+                HooksBackend::PureGo,
+                None,
+                None,
+                &UriRoot::Git,
+                &config,
+            )
+            .unwrap()
+            .remove("codspeed.go")
+            .unwrap()
+        };
+
+        assert!(
+            render(RawResultsTransport::Log).contains(r#"codspeedRawResultsTransport = "log""#)
+        );
+        assert!(
+            render(RawResultsTransport::Files).contains(r#"codspeedRawResultsTransport = "files""#)
+        );
+    }
+
+    #[test]
+    fn test_resolve_uri_root() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("repo")).unwrap();
+
+        let resolved =
+            resolve_uri_root(&UriRoot::Path(PathBuf::from("repo")), temp_dir.path()).unwrap();
+        assert_eq!(
+            resolved,
+            UriRoot::Path(temp_dir.path().join("repo").canonicalize().unwrap())
+        );
+
+        assert_eq!(
+            resolve_uri_root(&UriRoot::Module, temp_dir.path()).unwrap(),
+            UriRoot::Module
+        );
+        std::fs::write(temp_dir.path().join("go.mod"), "").unwrap();
+        for invalid in ["missing", "gti", "go.mod"] {
+            let result = resolve_uri_root(&UriRoot::Path(PathBuf::from(invalid)), temp_dir.path());
+            assert!(result.is_err(), "{invalid}");
+        }
+    }
+}
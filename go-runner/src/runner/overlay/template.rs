@@ -0,0 +1,265 @@
+//! Renderer for the overlay templates.
+//!
+//! Placeholders have the form `@@NAME@@` and their values are escaped depending on where they
+//! appear in the template:
+//! - Within a `#cgo` directive, the value is escaped as a cgo argument.
+//! - Within an `#include "..."` directive, the value is used as a C header path.
+//! - Within a Go string literal (`"..."`), the value is escaped as a Go string.
+//!
+//! Any other position is rejected, as well as unknown or malformed placeholders, so that a typo
+//! in a template can't silently end up in the generated Go code.
+
+use crate::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+static PLACEHOLDER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"@@([A-Za-z0-9_]+)@@").expect("valid placeholder regex"));
+
+/// Characters accepted by `go build` in `#cgo` arguments (see `safeCgoName` in `go/build`).
+/// Non-ASCII characters are accepted as well.
+const CGO_SAFE_CHARS: &str =
+    "+-.,/0123456789=ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz:$@%! ~^";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    CgoDirective,
+    CInclude,
+    GoString,
+}
+
+/// Renders `template` by replacing every `@@NAME@@` placeholder with the matching value.
+///
+/// `name` is only used to provide better error messages.
+pub fn render(name: &str, template: &str, values: &HashMap<&str, String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+
+    for (line_idx, line) in template.split_inclusive('\n').enumerate() {
+        let line_no = line_idx + 1;
+
+        let mut last_end = 0;
+        for captures in PLACEHOLDER_RE.captures_iter(line) {
+            let placeholder = captures.get(0).unwrap();
+            let key = &captures[1];
+
+            let literal = &line[last_end..placeholder.start()];
+            ensure_no_placeholder_leftovers(name, line_no, literal)?;
+            rendered.push_str(literal);
+
+            let Some(value) = values.get(key) else {
+                bail!("{name}:{line_no}: unknown placeholder `@@{key}@@`");
+            };
+            let Some(escape) = detect_escape(line, placeholder.start()) else {
+                bail!(
+                    "{name}:{line_no}: placeholder `@@{key}@@` must be used within a Go string \
+                     literal, a `#cgo` directive or an `#include` directive"
+                );
+            };
+            let escaped = match escape {
+                Escape::CgoDirective => escape_cgo_argument(value),
+                Escape::CInclude => escape_c_include(value),
+                Escape::GoString => Ok(escape_go_string(value)),
+            }
+            .with_context(|| format!("{name}:{line_no}: invalid value for `@@{key}@@`"))?;
+            rendered.push_str(&escaped);
+
+            last_end = placeholder.end();
+        }
+
+        let literal = &line[last_end..];
+        ensure_no_placeholder_leftovers(name, line_no, literal)?;
+        rendered.push_str(literal);
+    }
+
+    Ok(rendered)
+}
+
+fn ensure_no_placeholder_leftovers(name: &str, line_no: usize, literal: &str) -> Result<()> {
+    if literal.contains("@@") {
+        bail!(
+            "{name}:{line_no}: malformed placeholder in `{}`",
+            literal.trim()
+        );
+    }
+    Ok(())
+}
+
+fn detect_escape(line: &str, offset: usize) -> Option<Escape> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("#cgo ") {
+        return Some(Escape::CgoDirective);
+    }
+
+    if !is_within_string_literal(&line[..offset]) {
+        return None;
+    }
+    if trimmed.starts_with("#include") {
+        Some(Escape::CInclude)
+    } else {
+        Some(Escape::GoString)
+    }
+}
+
+/// Checks whether the end of `prefix` is within a double-quoted string literal.
+fn is_within_string_literal(prefix: &str) -> bool {
+    let mut within = false;
+    let mut escaped = false;
+    for c in prefix.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if within => escaped = true,
+            '"' => within = !within,
+            _ => {}
+        }
+    }
+    within
+}
+
+fn escape_go_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\\"),
+            '"' => escaped.push_str(r#"\""#),
+            '\n' => escaped.push_str(r"\n"),
+            '\r' => escaped.push_str(r"\r"),
+            '\t' => escaped.push_str(r"\t"),
+            c if c.is_control() => escaped.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes a value used within a `#cgo` argument. Spaces are escaped with a backslash, which is
+/// supported by the quoting rules of `go build`. Characters that `go build` refuses in cgo
+/// arguments are rejected.
+fn escape_cgo_argument(value: &str) -> Result<String> {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ' ' => escaped.push_str(r"\ "),
+            c if !c.is_ascii() || CGO_SAFE_CHARS.contains(c) => escaped.push(c),
+            c => bail!("character {c:?} is not allowed in a cgo directive: {value:?}"),
+        }
+    }
+    Ok(escaped)
+}
+
+/// C doesn't support escape sequences in header names, so we can only reject values that would
+/// terminate or corrupt the `#include "..."` directive.
+fn escape_c_include(value: &str) -> Result<String> {
+    if let Some(c) = value
+        .chars()
+        .find(|&c| c == '"' || c == '\\' || c.is_control())
+    {
+        bail!("character {c:?} is not allowed in an #include path: {value:?}");
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_go_string() {
+        let template = "profileDir := \"@@DIR@@\" // NOTE\n";
+        let rendered = render("test.go", template, &values(&[("DIR", "/tmp/profile")])).unwrap();
+        assert_eq!(rendered, "profileDir := \"/tmp/profile\" // NOTE\n");
+    }
+
+    #[test]
+    fn test_render_go_string_with_hostile_path() {
+        let template = "dir := \"@@DIR@@\"";
+        let hostile = "/tmp/a \"quoted\"\\dir\nwith\tcontrol\u{7}";
+        let rendered = render("test.go", template, &values(&[("DIR", hostile)])).unwrap();
+        assert_eq!(
+            rendered,
+            r#"dir := "/tmp/a \"quoted\"\\dir\nwith\tcontrol\u0007""#
+        );
+    }
+
+    #[test]
+    fn test_render_placeholder_after_escaped_quote() {
+        let template = r#"s := "a\"b @@DIR@@""#;
+        let rendered = render("test.go", template, &values(&[("DIR", "x\"y")])).unwrap();
+        assert_eq!(rendered, r#"s := "a\"b x\"y""#);
+    }
+
+    #[test]
+    fn test_render_cgo_directive() {
+        let template = "#cgo CFLAGS: -I@@DIR@@/includes -Wno-format\n#include \"@@DIR@@/core.c\"\n";
+        let rendered = render("test.go", template, &values(&[("DIR", "/tmp/my hooks")])).unwrap();
+        assert_eq!(
+            rendered,
+            "#cgo CFLAGS: -I/tmp/my\\ hooks/includes -Wno-format\n#include \"/tmp/my hooks/core.c\"\n"
+        );
+    }
+
+    #[test]
+    fn test_render_rejects_hostile_cgo_paths() {
+        for hostile in [
+            "/tmp/a\"b",
+            "/tmp/a\\b",
+            "/tmp/a'b",
+            "/tmp/a;b",
+            "/tmp/a\nb",
+        ] {
+            let template = "#cgo CFLAGS: -I@@DIR@@/includes";
+            let result = render("test.go", template, &values(&[("DIR", hostile)]));
+            assert!(result.is_err(), "expected {hostile:?} to be rejected");
+        }
+    }
+
+    #[test]
+    fn test_render_rejects_hostile_include_paths() {
+        for hostile in ["/tmp/a\"b", "/tmp/a\\b", "/tmp/a\nb"] {
+            let template = "#include \"@@DIR@@/core.c\"";
+            let result = render("test.go", template, &values(&[("DIR", hostile)]));
+            assert!(result.is_err(), "expected {hostile:?} to be rejected");
+        }
+    }
+
+    #[test]
+    fn test_render_rejects_unknown_placeholder() {
+        let template = "dir := \"@@CODSPEED_PROFIL_DIR@@\"";
+        let error = render(
+            "test.go",
+            template,
+            &values(&[("CODSPEED_PROFILE_DIR", "/tmp")]),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("test.go:1"), "{error}");
+        assert!(error.contains("@@CODSPEED_PROFIL_DIR@@"), "{error}");
+    }
+
+    #[test]
+    fn test_render_rejects_malformed_placeholder() {
+        let template = "ok := \"@@DIR@@\"\ndir := \"@@DIR@\"";
+        let error = render("test.go", template, &values(&[("DIR", "/tmp")]))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("test.go:2"), "{error}");
+    }
+
+    #[test]
+    fn test_render_rejects_placeholder_outside_string() {
+        let template = "var x = @@DIR@@";
+        let result = render("test.go", template, &values(&[("DIR", "/tmp")]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_values_are_not_rendered_recursively() {
+        let template = "a := \"@@A@@\"";
+        let rendered = render("test.go", template, &values(&[("A", "@@B@@")])).unwrap();
+        assert_eq!(rendered, "a := \"@@B@@\"");
+    }
+}
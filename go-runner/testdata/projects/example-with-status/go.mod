@@ -0,0 +1,3 @@
+module example-with-status
+
+go 1.24.5
@@ -0,0 +1,28 @@
+package examplewithstatus
+
+import "testing"
+
+func BenchmarkOk(b *testing.B) {
+	for b.Loop() {
+	}
+}
+
+func BenchmarkSkipped(b *testing.B) {
+	b.Skip("not supported on this platform")
+}
+
+func BenchmarkFailed(b *testing.B) {
+	b.Fatal("something went wrong")
+}
+
+var measuredRuns int
+
+// Skips after the initial run with b.N = 1, i.e. while the benchmark is measured.
+func BenchmarkSkippedWhileMeasured(b *testing.B) {
+	measuredRuns++
+	if measuredRuns > 1 {
+		b.Skip("skipped during the measured run")
+	}
+	for b.Loop() {
+	}
+}
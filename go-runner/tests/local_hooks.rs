@@ -0,0 +1,39 @@
+use codspeed_go_runner::{cli::Cli, results::walltime_results::WalltimeResults};
+use tempfile::TempDir;
+
+#[test]
+pub fn test_local_hooks_receive_pure_go_environment() {
+    // --local-hooks always uses the pure-Go hooks, which connect to the FIFOs of the go-runner
+    let profile_dir = TempDir::new().unwrap();
+    let cli = Cli {
+        bench: "BenchmarkBar1".to_string(),
+        benchtime: "1x".to_string(),
+        packages: vec!["./bar".to_string()],
+        local_hooks: true,
+        ..Default::default()
+    };
+    codspeed_go_runner::run_benchmarks(
+        profile_dir.path(),
+        std::path::Path::new("tests/pkg_arg.in"),
+        &cli,
+    )
+    .unwrap();
+
+    let results_dir = profile_dir.path().join("results");
+    let results_file = std::fs::read_dir(&results_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let results: WalltimeResults =
+        serde_json::from_reader(std::fs::File::open(results_file).unwrap()).unwrap();
+
+    // Only received through the FIFO, the environment files aren't attached to the results
+    let hooks = results.instrument_hooks.unwrap();
+    assert_eq!(hooks.integration.unwrap().name, "codspeed-go");
+    assert!(hooks.environment["go"]["version"].starts_with("go"));
+    assert!(hooks.environment["go.build"].contains_key("CGO_ENABLED"));
+    assert!(!hooks.executed_benchmarks.is_empty());
+    assert!(!hooks.markers.is_empty());
+}
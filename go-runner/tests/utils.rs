@@ -1,9 +1,10 @@
-use codspeed_go_runner::{cli::Cli, runner};
+use codspeed_go_runner::{cli::Cli, config::Config, runner};
 use std::path::Path;
 
 /// Helper function to run a single package using CLI configuration
 pub fn run_with_cli<P: AsRef<Path>>(dir: P, cli: &Cli) -> anyhow::Result<String> {
     assert!(dir.as_ref().exists());
-    let stdout = runner::run_with_stdout(Path::new("/tmp"), dir.as_ref(), cli)?;
+    let config = Config::from_cli(cli)?;
+    let stdout = runner::run_with_stdout(Path::new("/tmp"), dir.as_ref(), cli, &config)?;
     Ok(stdout)
 }